@@ -0,0 +1,249 @@
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// An addr-spec longer than this many bytes cannot be used in SMTP.
+const MAX_ADDRESS_LEN: usize = 254;
+
+/// A local part longer than this many bytes cannot be used in SMTP.
+const MAX_LOCAL_LEN: usize = 64;
+
+/// A domain longer than this many bytes cannot be resolved.
+const MAX_DOMAIN_LEN: usize = 255;
+
+/// A domain label longer than this many bytes cannot be resolved.
+const MAX_LABEL_LEN: usize = 63;
+
+/// Why a string is not an email address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressError {
+    /// The string is empty or only holds whitespace.
+    Empty,
+    /// There is no `@` between the local part and the domain.
+    MissingAt,
+    /// The local part is neither a dot-atom nor a quoted string.
+    InvalidLocalPart,
+    /// The domain is neither a list of labels nor a domain literal.
+    InvalidDomain,
+    /// The text before `<` is not a valid display name.
+    InvalidDisplayName,
+    /// The address, its local part or its domain is too long for SMTP.
+    TooLong,
+    /// A domain label cannot be converted between its Unicode and its
+    /// ASCII-compatible form.
+    InvalidIdn,
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AddressError::Empty => write!(f, "the address is empty"),
+            AddressError::MissingAt => write!(f, "the address has no `@`"),
+            AddressError::InvalidLocalPart => write!(f, "the local part is invalid"),
+            AddressError::InvalidDomain => write!(f, "the domain is invalid"),
+            AddressError::InvalidDisplayName => write!(f, "the display name is invalid"),
+            AddressError::TooLong => write!(f, "the address is too long"),
+            AddressError::InvalidIdn => write!(f, "the domain is not a valid internationalized domain name"),
+        }
+    }
+}
+
+impl error::Error for AddressError {}
+
+/// An email address, as the addr-spec of RFC 5322: a local part, which may
+/// be a quoted string, and a domain, which may be a domain literal.
+///
+/// It is parsed with `str::parse`, from a bare address or from the
+/// `Name <address>` form, whose display name is checked and dropped.
+/// Non-ASCII characters are accepted, as allowed by RFC 6531.
+///
+/// # Examples
+///
+/// ```
+/// use smtpapi::{EmailAddress, AddressError};
+///
+/// let address: EmailAddress = "John Doe <john.doe@domain.com>".parse().unwrap();
+/// assert_eq!("john.doe", address.local_part());
+/// assert_eq!("domain.com", address.domain());
+/// assert_eq!("john.doe@domain.com", address.to_string());
+///
+/// assert!("\"john doe\"@domain.com".parse::<EmailAddress>().is_ok());
+/// assert_eq!(Err(AddressError::InvalidDomain), "bob@".parse::<EmailAddress>());
+/// assert_eq!(Err(AddressError::InvalidLocalPart), "Bob Smith bob@domain.com".parse::<EmailAddress>());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EmailAddress {
+    local: String,
+    domain: String
+}
+
+impl EmailAddress {
+    /// Returns the part before the `@`, with its quotes if it is quoted.
+    pub fn local_part(&self) -> &str {
+        &self.local
+    }
+
+    /// Returns the part after the `@`.
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    /// Returns the address with the same local part and another domain.
+    #[cfg(feature = "idna")]
+    pub(crate) fn with_domain(&self, domain: String) -> EmailAddress {
+        EmailAddress { local: self.local.clone(), domain }
+    }
+}
+
+impl fmt::Display for EmailAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}@{}", self.local, self.domain)
+    }
+}
+
+impl FromStr for EmailAddress {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<EmailAddress, AddressError> {
+        parse_mailbox(s).map(|(_, address)| address)
+    }
+}
+
+impl From<EmailAddress> for String {
+    fn from(address: EmailAddress) -> String {
+        address.to_string()
+    }
+}
+
+fn is_atext(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c) || !c.is_ascii()
+}
+
+fn is_dot_atom(s: &str) -> bool {
+    !s.is_empty() && s.split('.').all(|atom| !atom.is_empty() && atom.chars().all(is_atext))
+}
+
+/// Returns true for a quoted string: printable characters, spaces and
+/// backslash escapes between double quotes.
+fn is_quoted_string(s: &str) -> bool {
+    if s.len() < 2 || !s.starts_with('"') || !s.ends_with('"') {
+        return false;
+    }
+
+    let mut chars = s[1..s.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.next().is_none_or(|c| c.is_control() && c != '\t') => return false,
+            '"' => return false,
+            c if c.is_control() && c != '\t' => return false,
+            _ => {},
+        }
+    }
+
+    true
+}
+
+fn is_label(label: &str) -> bool {
+    !label.is_empty() && label.len() <= MAX_LABEL_LEN &&
+        !label.starts_with('-') && !label.ends_with('-') &&
+        label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || !c.is_ascii())
+}
+
+fn is_domain(domain: &str) -> bool {
+    if domain.starts_with('[') && domain.ends_with(']') && domain.len() > 2 {
+        return domain[1..domain.len() - 1].chars().all(|c| c.is_ascii_graphic() && c != '[' && c != ']' && c != '\\');
+    }
+
+    domain.split('.').all(is_label)
+}
+
+/// Returns true for a display name: words made of atext, dots and spaces,
+/// or a single quoted string.
+fn is_display_name(name: &str) -> bool {
+    is_quoted_string(name) || name.chars().all(|c| is_atext(c) || c == ' ' || c == '\t' || c == '.')
+}
+
+fn parse_addr_spec(s: &str) -> Result<EmailAddress, AddressError> {
+    let at = s.rfind('@').ok_or(AddressError::MissingAt)?;
+    let (local, domain) = (&s[..at], &s[at + 1..]);
+
+    if !is_dot_atom(local) && !is_quoted_string(local) {
+        return Err(AddressError::InvalidLocalPart);
+    }
+    if !is_domain(domain) {
+        return Err(AddressError::InvalidDomain);
+    }
+    if s.len() > MAX_ADDRESS_LEN || local.len() > MAX_LOCAL_LEN || domain.len() > MAX_DOMAIN_LEN {
+        return Err(AddressError::TooLong);
+    }
+
+    Ok(EmailAddress { local: local.to_owned(), domain: domain.to_owned() })
+}
+
+/// Parses a bare addr-spec or the `Name <addr-spec>` form, returning the
+/// display name as written, quotes included, when there is one.
+pub(crate) fn parse_mailbox(s: &str) -> Result<(Option<&str>, EmailAddress), AddressError> {
+    let s = s.trim();
+
+    if s.is_empty() {
+        return Err(AddressError::Empty);
+    }
+
+    if s.ends_with('>') {
+        if let Some(open) = s.rfind('<') {
+            let name = s[..open].trim();
+            if !is_display_name(name) {
+                return Err(AddressError::InvalidDisplayName);
+            }
+            let address = parse_addr_spec(&s[open + 1..s.len() - 1])?;
+            return Ok((if name.is_empty() { None } else { Some(name) }, address));
+        }
+    }
+
+    parse_addr_spec(s).map(|address| (None, address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<EmailAddress, AddressError> {
+        s.parse()
+    }
+
+    #[test]
+    fn valid_addresses() {
+        for s in &["email@domain.com", "first.last+tag@sub.domain.com", "o'hara@domain.ie",
+                   "\"john doe\"@domain.com", "\"john\\\"doe\"@domain.com", "user@localhost",
+                   "user@[192.168.0.1]", "用户@例子.广告", "  email@domain.com  "] {
+            assert!(parse(s).is_ok(), "{} should be valid", s);
+        }
+    }
+
+    #[test]
+    fn invalid_addresses() {
+        assert_eq!(Err(AddressError::Empty), parse("  "));
+        assert_eq!(Err(AddressError::MissingAt), parse("email.domain.com"));
+        assert_eq!(Err(AddressError::InvalidLocalPart), parse("@domain.com"));
+        assert_eq!(Err(AddressError::InvalidLocalPart), parse(".email@domain.com"));
+        assert_eq!(Err(AddressError::InvalidLocalPart), parse("first..last@domain.com"));
+        assert_eq!(Err(AddressError::InvalidLocalPart), parse("john doe@domain.com"));
+        assert_eq!(Err(AddressError::InvalidLocalPart), parse("\"john\"doe\"@domain.com"));
+        assert_eq!(Err(AddressError::InvalidDomain), parse("email@"));
+        assert_eq!(Err(AddressError::InvalidDomain), parse("email@domain..com"));
+        assert_eq!(Err(AddressError::InvalidDomain), parse("email@-domain.com"));
+        assert_eq!(Err(AddressError::InvalidDomain), parse("email@domain.com."));
+        assert_eq!(Err(AddressError::TooLong), parse(&format!("{}@domain.com", "a".repeat(65))));
+        assert_eq!(Err(AddressError::InvalidDomain), parse(&format!("email@{}.com", "a".repeat(64))));
+    }
+
+    #[test]
+    fn name_addr_form() {
+        assert_eq!(Ok((Some("John Doe"), parse("john@domain.com").unwrap())), parse_mailbox("John Doe <john@domain.com>"));
+        assert_eq!(Ok((Some("\"Doe, John\""), parse("john@domain.com").unwrap())), parse_mailbox("\"Doe, John\" <john@domain.com>"));
+        assert_eq!(Ok((None, parse("john@domain.com").unwrap())), parse_mailbox("<john@domain.com>"));
+        assert_eq!(Err(AddressError::InvalidDisplayName), parse_mailbox("Doe, John <john@domain.com>"));
+        assert_eq!(Err(AddressError::InvalidDomain), parse_mailbox("John <john@>"));
+        assert_eq!(Err(AddressError::MissingAt), parse_mailbox("John Doe <john>"));
+    }
+}
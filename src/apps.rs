@@ -0,0 +1,232 @@
+//! Typed builders for the SendGrid apps that can be configured through the
+//! `filters` field of X-SMTPAPI.
+//!
+//! Each builder produces a `Filter` holding the setting names SendGrid
+//! documents for the app, and is added to a `Header` with
+//! `Header::set_app`. Apps that have no builder here can still be set with
+//! `Header::set_filter`.
+//!
+//! # Examples
+//!
+//! ```
+//! use smtpapi::Header;
+//! use smtpapi::apps::{ClickTrack, Footer};
+//!
+//! let mut header = Header::new();
+//! header.set_app(ClickTrack::new().enable_text(true))
+//!       .set_app(Footer::new().text_plain("Sent with love"));
+//!
+//! assert_eq!("{\"filters\":{\"clicktrack\":{\"settings\":{\"enable\":\"1\",\"enable_text\":\"1\"}},\"footer\":{\"settings\":{\"enable\":\"1\",\"text/plain\":\"Sent with love\"}}}}", header.to_json_string());
+//! ```
+
+use super::Filter;
+
+/// A SendGrid app that can be turned into the `Filter` of the same name.
+pub trait App {
+    /// Returns the key of the app in the `filters` field.
+    fn name(&self) -> &'static str;
+
+    /// Returns the settings of the app.
+    fn to_filter(&self) -> Filter;
+}
+
+fn flag(value: bool) -> &'static str {
+    if value { "1" } else { "0" }
+}
+
+macro_rules! app {
+    ($(#[$doc:meta])* $app:ident, $name:expr) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone)]
+        pub struct $app {
+            filter: Filter
+        }
+
+        impl $app {
+            /// It turns the app on; call `enable(false)` to turn it off.
+            pub fn new() -> $app {
+                let mut filter = Filter::new();
+                filter.add_setting("enable", "1");
+                $app { filter }
+            }
+
+            /// It turns the app on or off.
+            pub fn enable(&mut self, enable: bool) -> &mut $app {
+                self.set("enable", flag(enable))
+            }
+
+            fn set<S>(&mut self, setting: &str, value: S) -> &mut $app where S: Into<String> {
+                self.filter.add_setting(setting.to_owned(), value.into());
+                self
+            }
+        }
+
+        impl Default for $app {
+            fn default() -> $app {
+                $app::new()
+            }
+        }
+
+        impl App for $app {
+            fn name(&self) -> &'static str {
+                $name
+            }
+
+            fn to_filter(&self) -> Filter {
+                self.filter.clone()
+            }
+        }
+
+        impl From<$app> for Filter {
+            fn from(app: $app) -> Filter {
+                app.to_filter()
+            }
+        }
+    }
+}
+
+app!(
+    /// Rewrites links to track clicks (`clicktrack`).
+    ClickTrack, "clicktrack");
+
+impl ClickTrack {
+    /// It also tracks links in the plain text part.
+    pub fn enable_text(&mut self, enable: bool) -> &mut ClickTrack {
+        self.set("enable_text", flag(enable))
+    }
+}
+
+app!(
+    /// Adds an invisible image to track opens (`opentrack`).
+    OpenTrack, "opentrack");
+
+impl OpenTrack {
+    /// It replaces this tag with the tracking pixel instead of appending it.
+    pub fn replace<S>(&mut self, tag: S) -> &mut OpenTrack where S: Into<String> {
+        self.set("replace", tag)
+    }
+}
+
+app!(
+    /// Appends a footer to the message (`footer`).
+    Footer, "footer");
+
+impl Footer {
+    /// It sets the footer of the HTML part.
+    pub fn text_html<S>(&mut self, html: S) -> &mut Footer where S: Into<String> {
+        self.set("text/html", html)
+    }
+
+    /// It sets the footer of the plain text part.
+    pub fn text_plain<S>(&mut self, text: S) -> &mut Footer where S: Into<String> {
+        self.set("text/plain", text)
+    }
+}
+
+app!(
+    /// Sends a blind copy of every message to an address (`bcc`).
+    Bcc, "bcc");
+
+impl Bcc {
+    /// It sets the address receiving the copies.
+    pub fn email<S>(&mut self, email: S) -> &mut Bcc where S: Into<String> {
+        self.set("email", email)
+    }
+}
+
+app!(
+    /// Delivers regardless of unsubscribe, bounce and spam report lists
+    /// (`bypass_list_management`).
+    BypassListManagement, "bypass_list_management");
+
+app!(
+    /// Adds Google Analytics parameters to links (`ganalytics`).
+    GAnalytics, "ganalytics");
+
+impl GAnalytics {
+    /// It sets the `utm_source` parameter.
+    pub fn utm_source<S>(&mut self, value: S) -> &mut GAnalytics where S: Into<String> {
+        self.set("utm_source", value)
+    }
+
+    /// It sets the `utm_medium` parameter.
+    pub fn utm_medium<S>(&mut self, value: S) -> &mut GAnalytics where S: Into<String> {
+        self.set("utm_medium", value)
+    }
+
+    /// It sets the `utm_term` parameter.
+    pub fn utm_term<S>(&mut self, value: S) -> &mut GAnalytics where S: Into<String> {
+        self.set("utm_term", value)
+    }
+
+    /// It sets the `utm_content` parameter.
+    pub fn utm_content<S>(&mut self, value: S) -> &mut GAnalytics where S: Into<String> {
+        self.set("utm_content", value)
+    }
+
+    /// It sets the `utm_campaign` parameter.
+    pub fn utm_campaign<S>(&mut self, value: S) -> &mut GAnalytics where S: Into<String> {
+        self.set("utm_campaign", value)
+    }
+}
+
+app!(
+    /// Adds an unsubscribe link to the message (`subscriptiontrack`).
+    SubscriptionTrack, "subscriptiontrack");
+
+impl SubscriptionTrack {
+    /// It sets the HTML appended to the message; `<% %>` marks the link text.
+    pub fn text_html<S>(&mut self, html: S) -> &mut SubscriptionTrack where S: Into<String> {
+        self.set("text/html", html)
+    }
+
+    /// It sets the text appended to the plain part; `<% %>` marks the link.
+    pub fn text_plain<S>(&mut self, text: S) -> &mut SubscriptionTrack where S: Into<String> {
+        self.set("text/plain", text)
+    }
+
+    /// It replaces this tag with the unsubscribe link instead of appending text.
+    pub fn replace<S>(&mut self, tag: S) -> &mut SubscriptionTrack where S: Into<String> {
+        self.set("replace", tag)
+    }
+
+    /// It sends unsubscribing recipients to this landing page.
+    pub fn landing<S>(&mut self, url: S) -> &mut SubscriptionTrack where S: Into<String> {
+        self.set("landing", url)
+    }
+
+    /// It uses this URL as the unsubscribe link.
+    pub fn url<S>(&mut self, url: S) -> &mut SubscriptionTrack where S: Into<String> {
+        self.set("url", url)
+    }
+}
+
+app!(
+    /// Drops messages that SpamAssassin scores too high (`spamcheck`).
+    SpamCheck, "spamcheck");
+
+impl SpamCheck {
+    /// It sets the score, from 1 to 10, above which messages are dropped.
+    ///
+    /// The score is not checked here; `Header::validate` reports one that is
+    /// out of range or not a number.
+    pub fn maxscore(&mut self, score: f32) -> &mut SpamCheck {
+        self.set("maxscore", score.to_string())
+    }
+
+    /// It posts a copy of dropped messages to this URL.
+    pub fn url<S>(&mut self, url: S) -> &mut SpamCheck where S: Into<String> {
+        self.set("url", url)
+    }
+}
+
+app!(
+    /// Wraps the message in a transactional template (`templates`).
+    Templates, "templates");
+
+impl Templates {
+    /// It sets the ID of the template to use.
+    pub fn template_id<S>(&mut self, id: S) -> &mut Templates where S: Into<String> {
+        self.set("template_id", id)
+    }
+}
@@ -0,0 +1,211 @@
+use super::{EmailAddress, Filter, Header, SmtpApiError};
+use super::apps::App;
+use super::encoding::Encoding;
+use super::validate;
+
+/// A strict way of building a `Header`.
+///
+/// The setters mirror those of `Header`, but nothing is silently fixed or
+/// discarded: setting both `send_at` and `send_each_at` is kept as a
+/// conflict, and `build()` checks the result against the X-SMTPAPI rules
+/// instead of letting SendGrid reject the message.
+///
+/// # Examples
+///
+/// ```
+/// use smtpapi::{AddressError, HeaderBuilder, SmtpApiError};
+///
+/// let header = HeaderBuilder::new()
+///     .add_recipient("john@domain.com", vec![("-name-", "John")])
+///     .add_category("welcome")
+///     .build()
+///     .unwrap();
+/// assert_eq!("{\"category\":[\"welcome\"],\"sub\":{\"-name-\":[\"John\"]},\"to\":[\"john@domain.com\"]}", header.to_json_string());
+///
+/// let error = HeaderBuilder::new().add_to("john@").build().unwrap_err();
+/// assert_eq!(SmtpApiError::InvalidAddress { index: 0, email: "john@".to_string(), reason: AddressError::InvalidDomain }, error);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HeaderBuilder {
+    header: Header,
+    max_size: Option<usize>
+}
+
+impl HeaderBuilder {
+    /// Constructs a new `HeaderBuilder`.
+    pub fn new() -> HeaderBuilder {
+        HeaderBuilder { header: Header::new(), max_size: None }
+    }
+
+    /// It appends a single email to the To header
+    pub fn add_to<S>(&mut self, email: S) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.add_to(email);
+        self
+    }
+
+    /// It appends multiple emails to the To header
+    pub fn add_tos<S>(&mut self, emails: Vec<S>) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.add_tos(emails);
+        self
+    }
+
+    /// It appends a recipient together with its substitution values, see `Header::add_recipient`
+    ///
+    /// When a substitution column is already longer than the To list, the
+    /// recipient is not added and `build()` reports the misaligned column.
+    pub fn add_recipient<S, I, K, V>(&mut self, email: S, subs: I) -> &mut HeaderBuilder
+        where S: Into<String>, I: IntoIterator<Item = (K, V)>, K: Into<String>, V: Into<String> {
+        let _ = self.header.add_recipient(email, subs);
+        self
+    }
+
+    /// It sets the substitution value used by `add_recipient` for omitted tags
+    pub fn set_substitution_default<S>(&mut self, value: S) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.set_substitution_default(value);
+        self
+    }
+
+    /// It adds a new substitution to a specific key
+    pub fn add_substitution<S>(&mut self, key: S, sub: S) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.add_substitution(key, sub);
+        self
+    }
+
+    /// It adds a multiple substitutions to a specific key
+    pub fn add_substitutions<S>(&mut self, key: S, subs: Vec<&str>) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.add_substitutions(key, subs);
+        self
+    }
+
+    /// It sets the value for a specific section
+    pub fn add_section<S>(&mut self, section: S, value: S) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.add_section(section, value);
+        self
+    }
+
+    /// It adds a new category to the Category header
+    pub fn add_category<S>(&mut self, category: S) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.add_category(category);
+        self
+    }
+
+    /// It adds multiple categories to the Category header
+    pub fn add_categories<S>(&mut self, categories: Vec<S>) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.add_categories(categories);
+        self
+    }
+
+    /// It sets the value of a specific unique argument
+    pub fn add_unique_arg<S>(&mut self, unique_arg: S, value: S) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.add_unique_arg(unique_arg, value);
+        self
+    }
+
+    /// It will set the specific setting for a filter
+    pub fn add_filter<S>(&mut self, filter_name: S, setting: S, value: S) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.add_filter(filter_name, setting, value);
+        self
+    }
+
+    /// It takes in a Filter struct with predetermined settings and sets it for such Filter key
+    pub fn set_filter<S>(&mut self, filter: S, setting: Filter) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.set_filter(filter, setting);
+        self
+    }
+
+    /// It sets the settings of a SendGrid app, replacing any previous ones
+    pub fn set_app<A>(&mut self, app: &A) -> &mut HeaderBuilder where A: App + ?Sized {
+        self.header.set_app(app);
+        self
+    }
+
+    /// It sets the value of the IpPool field
+    pub fn set_ip_pool<S>(&mut self, name: S) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.set_ip_pool(name);
+        self
+    }
+
+    /// It sets the batch ID, which groups scheduled sends
+    pub fn set_batch_id<S>(&mut self, batch_id: S) -> &mut HeaderBuilder where S: Into<String> {
+        self.header.set_batch_id(batch_id);
+        self
+    }
+
+    /// It will set the value of the ASMGroupID field
+    pub fn set_asm_group_id(&mut self, asm_group_id: i32) -> &mut HeaderBuilder {
+        self.header.set_asm_group_id(asm_group_id);
+        self
+    }
+
+    /// It takes in a timestamp which determines when the email will be sent
+    ///
+    /// Unlike `Header::set_send_at`, `send_each_at` is kept, and `build()`
+    /// reports the conflict if both are set.
+    pub fn set_send_at(&mut self, send_at: i64) -> &mut HeaderBuilder {
+        self.header.send_at = Some(send_at);
+        self
+    }
+
+    /// It takes in a timestamp and pushes it into a list. Must match length of To emails
+    ///
+    /// Unlike `Header::add_send_each_at`, `send_at` is kept, and `build()`
+    /// reports the conflict if both are set.
+    pub fn add_send_each_at(&mut self, send_at: i64) -> &mut HeaderBuilder {
+        self.header.send_each_at.get_or_insert_with(Vec::new).push(send_at);
+        self
+    }
+
+    /// It takes an array of timestamps. Must match length of To emails
+    ///
+    /// Unlike `Header::set_send_each_at`, `send_at` is kept, and `build()`
+    /// reports the conflict if both are set.
+    pub fn set_send_each_at(&mut self, send_each_at: Vec<i64>) -> &mut HeaderBuilder {
+        self.header.send_each_at = Some(send_each_at);
+        self
+    }
+
+    /// It sets how non-ASCII characters are written by the built `Header`
+    pub fn set_encoding(&mut self, encoding: Encoding) -> &mut HeaderBuilder {
+        self.header.set_encoding(encoding);
+        self
+    }
+
+    /// It sets the largest `to_json_string()`, in bytes, that `build()` accepts
+    pub fn set_max_size(&mut self, max_bytes: usize) -> &mut HeaderBuilder {
+        self.max_size = Some(max_bytes);
+        self
+    }
+
+    /// Returns the `Header`, or the first rule it breaks
+    ///
+    /// Recipients are checked first, then the alignment of the substitution
+    /// columns and of `send_each_at`, the schedule, the other rules of
+    /// `Header::validate` and finally the size limit.
+    pub fn build(&self) -> Result<Header, SmtpApiError> {
+        for (index, email) in self.header.to.iter().enumerate() {
+            if let Err(reason) = email.parse::<EmailAddress>() {
+                return Err(SmtpApiError::InvalidAddress { index, email: email.clone(), reason });
+            }
+        }
+
+        if let Some(error) = validate::validate(&self.header).into_iter().next() {
+            return Err(error.into());
+        }
+
+        if let Some(max) = self.max_size {
+            let len = self.header.to_json_string().len();
+            if len > max {
+                return Err(SmtpApiError::TooLarge { len, max });
+            }
+        }
+
+        Ok(self.header.clone())
+    }
+}
+
+/// Starts from an existing `Header`, so that `build()` checks it
+impl From<Header> for HeaderBuilder {
+    fn from(header: Header) -> HeaderBuilder {
+        HeaderBuilder { header, max_size: None }
+    }
+}
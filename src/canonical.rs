@@ -0,0 +1,41 @@
+use rustc_serialize::json::{Json, ToJson};
+
+use super::Header;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a. Unlike `std::collections::hash_map::DefaultHasher`, its
+/// output is fixed by its definition and never changes between releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME))
+}
+
+pub fn to_string(header: &Header) -> String {
+    let mut json = header.to_json();
+
+    if let Json::Object(ref mut fields) = json {
+        if let Some(&mut Json::Array(ref mut categories)) = fields.get_mut("category") {
+            categories.sort_by(|a, b| a.as_string().cmp(&b.as_string()));
+            categories.dedup();
+        }
+    }
+
+    json.to_string()
+}
+
+pub fn hash(header: &Header) -> u64 {
+    fnv1a(to_string(header).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_known_values() {
+        assert_eq!(0xcbf2_9ce4_8422_2325, fnv1a(b""));
+        assert_eq!(0xaf63_dc4c_8601_ec8c, fnv1a(b"a"));
+        assert_eq!(0x8594_4171_f739_67e8, fnv1a(b"foobar"));
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::{BTreeMap, HashMap};
+use std::error;
+use std::fmt;
+use rustc_serialize::json::{Json, ParserError};
+
+use super::{Filter, Header};
+
+/// Error returned when an X-SMTPAPI JSON value cannot be turned into a `Header`.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The input is not valid JSON.
+    Syntax(ParserError),
+    /// The top level JSON value is not an object.
+    NotAnObject,
+    /// The object contains a key that is not part of the X-SMTPAPI format.
+    UnknownField(String),
+    /// A field holds a value of the wrong type; `field` is the dotted path to it.
+    InvalidField { field: String, expected: &'static str },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::Syntax(ref e) => write!(f, "invalid JSON: {}", e),
+            ParseError::NotAnObject => write!(f, "X-SMTPAPI value must be a JSON object"),
+            ParseError::UnknownField(ref name) => write!(f, "unknown field `{}`", name),
+            ParseError::InvalidField { ref field, expected } => {
+                write!(f, "field `{}` must be {}", field, expected)
+            }
+        }
+    }
+}
+
+impl error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ParseError::Syntax(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParserError> for ParseError {
+    fn from(e: ParserError) -> ParseError {
+        ParseError::Syntax(e)
+    }
+}
+
+fn invalid(field: &str, expected: &'static str) -> ParseError {
+    ParseError::InvalidField { field: field.to_owned(), expected }
+}
+
+fn object<'a>(value: &'a Json, field: &str) -> Result<&'a BTreeMap<String, Json>, ParseError> {
+    value.as_object().ok_or_else(|| invalid(field, "an object"))
+}
+
+fn string(value: &Json, field: &str) -> Result<String, ParseError> {
+    value.as_string().map(|s| s.to_owned()).ok_or_else(|| invalid(field, "a string"))
+}
+
+fn strings(value: &Json, field: &str) -> Result<Vec<String>, ParseError> {
+    let items = value.as_array().ok_or_else(|| invalid(field, "an array of strings"))?;
+    items.iter()
+         .enumerate()
+         .map(|(i, item)| string(item, &format!("{}.{}", field, i)))
+         .collect()
+}
+
+fn timestamp(value: &Json, field: &str) -> Result<i64, ParseError> {
+    value.as_i64().ok_or_else(|| invalid(field, "an integer timestamp"))
+}
+
+fn string_map(value: &Json, field: &str) -> Result<HashMap<String, String>, ParseError> {
+    object(value, field)?
+        .iter()
+        .map(|(k, v)| Ok((k.clone(), string(v, &format!("{}.{}", field, k))?)))
+        .collect()
+}
+
+/// Filter settings are strings in `Header`, but other producers commonly
+/// send `1` or `true`, so scalars are accepted and kept in their JSON form.
+fn setting(value: &Json, field: &str) -> Result<String, ParseError> {
+    match *value {
+        Json::String(ref s) => Ok(s.clone()),
+        Json::I64(_) | Json::U64(_) | Json::F64(_) | Json::Boolean(_) => Ok(value.to_string()),
+        _ => Err(invalid(field, "a string, number or boolean")),
+    }
+}
+
+fn filter(value: &Json, field: &str) -> Result<Filter, ParseError> {
+    let mut filter = Filter::new();
+
+    for (key, value) in object(value, field)? {
+        let path = format!("{}.{}", field, key);
+        if key != "settings" {
+            return Err(ParseError::UnknownField(path));
+        }
+        for (name, v) in object(value, &path)? {
+            let s = setting(v, &format!("{}.{}", path, name))?;
+            filter.settings.insert(name.clone(), s);
+        }
+    }
+
+    Ok(filter)
+}
+
+pub fn header_from_json(json: Json) -> Result<Header, ParseError> {
+    let fields = match json {
+        Json::Object(fields) => fields,
+        _ => return Err(ParseError::NotAnObject),
+    };
+    let mut header = Header::new();
+
+    for (key, value) in &fields {
+        match key.as_str() {
+            "to" => header.to = strings(value, key)?,
+            "sub" => {
+                for (tag, values) in object(value, key)? {
+                    header.sub.insert(tag.clone(), strings(values, &format!("sub.{}", tag))?);
+                }
+            },
+            "section" => header.section = string_map(value, key)?,
+            // SendGrid accepts a single category as a bare string.
+            "category" => header.categories = match *value {
+                Json::String(ref s) => vec![s.clone()],
+                _ => strings(value, key)?,
+            },
+            "unique_args" => header.unique_args = string_map(value, key)?,
+            "filters" => {
+                for (name, settings) in object(value, key)? {
+                    header.filters.insert(name.clone(), filter(settings, &format!("filters.{}", name))?);
+                }
+            },
+            "asm_group_id" => {
+                let id = value.as_i64()
+                              .filter(|&id| id >= i32::MIN as i64 && id <= i32::MAX as i64)
+                              .ok_or_else(|| invalid(key, "a 32-bit integer"))?;
+                header.asm_group_id = Some(id as i32);
+            },
+            "send_at" => header.send_at = Some(timestamp(value, key)?),
+            "send_each_at" => {
+                let items = value.as_array().ok_or_else(|| invalid(key, "an array of integer timestamps"))?;
+                let timestamps = items.iter()
+                                      .enumerate()
+                                      .map(|(i, item)| timestamp(item, &format!("send_each_at.{}", i)))
+                                      .collect::<Result<Vec<i64>, ParseError>>()?;
+                header.send_each_at = Some(timestamps);
+            },
+            "ip_pool" => header.ip_pool = Some(string(value, key)?),
+            "batch_id" => header.batch_id = Some(string(value, key)?),
+            _ => return Err(ParseError::UnknownField(key.clone())),
+        }
+    }
+
+    Ok(header)
+}
@@ -0,0 +1,83 @@
+use std::collections::HashSet;
+
+use super::EmailAddress;
+
+/// Which occurrence of a duplicated recipient `Header::dedup_recipients` keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Occurrence {
+    /// The first occurrence in the To list, with its substitution values.
+    First,
+    /// The last occurrence in the To list, with its substitution values.
+    Last,
+}
+
+/// How `Header::dedup_recipients` decides that two recipients are the same.
+///
+/// Addresses are compared without their display name and with the domain
+/// lowercased. The default keeps the first occurrence and compares local
+/// parts as written, since RFC 5321 leaves their case to the mail server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DedupPolicy {
+    /// The occurrence kept.
+    pub keep: Occurrence,
+    /// Whether local parts are also compared case-insensitively.
+    pub fold_local_part: bool,
+}
+
+impl Default for DedupPolicy {
+    fn default() -> DedupPolicy {
+        DedupPolicy { keep: Occurrence::First, fold_local_part: false }
+    }
+}
+
+/// Returns the address `email` is compared by. Entries that are not email
+/// addresses are compared as written, without surrounding whitespace.
+fn normalize(email: &str, fold_local_part: bool) -> String {
+    match email.parse::<EmailAddress>() {
+        Ok(address) => {
+            let local = if fold_local_part { address.local_part().to_lowercase() } else { address.local_part().to_owned() };
+            format!("{}@{}", local, address.domain().to_lowercase())
+        },
+        Err(_) => email.trim().to_owned(),
+    }
+}
+
+/// Returns, for each entry of `to`, whether it is kept.
+pub fn kept(to: &[String], policy: DedupPolicy) -> Vec<bool> {
+    let mut seen = HashSet::new();
+    let mut kept = vec![false; to.len()];
+
+    let mut mark = |i: usize| kept[i] = seen.insert(normalize(&to[i], policy.fold_local_part));
+    match policy.keep {
+        Occurrence::First => (0..to.len()).for_each(&mut mark),
+        Occurrence::Last => (0..to.len()).rev().for_each(&mut mark),
+    }
+
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to(emails: &[&str]) -> Vec<String> {
+        emails.iter().map(|email| email.to_string()).collect()
+    }
+
+    #[test]
+    fn addresses_are_normalized() {
+        assert_eq!("John@domain.com", normalize("John Doe <John@Domain.COM>", false));
+        assert_eq!("john@domain.com", normalize("John@Domain.COM", true));
+        assert_eq!("not an address", normalize(" not an address ", true));
+    }
+
+    #[test]
+    fn first_or_last_occurrence_is_kept() {
+        let emails = to(&["a@domain.com", "b@domain.com", "a@DOMAIN.com", "A@domain.com"]);
+
+        assert_eq!(vec![true, true, false, true], kept(&emails, DedupPolicy::default()));
+        assert_eq!(vec![true, true, false, false], kept(&emails, DedupPolicy { fold_local_part: true, ..DedupPolicy::default() }));
+        assert_eq!(vec![false, true, true, true], kept(&emails, DedupPolicy { keep: Occurrence::Last, ..DedupPolicy::default() }));
+        assert_eq!(vec![false, true, false, true], kept(&emails, DedupPolicy { keep: Occurrence::Last, fold_local_part: true }));
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+
+use super::Header;
+
+/// A field-level difference between two `Header`s, as reported by `Header::diff`.
+///
+/// Recipients are matched by address, in To list order: `index` is the
+/// position in the old `Header` for removed recipients and in the new one
+/// otherwise. A value of `None` means the entry is not set on that side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A recipient is only in the new `Header`.
+    RecipientAdded { index: usize, email: String },
+    /// A recipient is only in the old `Header`.
+    RecipientRemoved { index: usize, email: String },
+    /// The substitution value of a recipient kept in both `Header`s changed.
+    SubstitutionChanged { tag: String, index: usize, email: String, old: Option<String>, new: Option<String> },
+    /// The `send_each_at` timestamp of a recipient kept in both `Header`s changed.
+    SendEachAtChanged { index: usize, email: String, old: Option<i64>, new: Option<i64> },
+    /// A section changed.
+    SectionChanged { section: String, old: Option<String>, new: Option<String> },
+    /// A category is only in the new `Header`.
+    CategoryAdded(String),
+    /// A category is only in the old `Header`.
+    CategoryRemoved(String),
+    /// A unique argument changed.
+    UniqueArgChanged { unique_arg: String, old: Option<String>, new: Option<String> },
+    /// A filter setting changed.
+    FilterSettingChanged { filter: String, setting: String, old: Option<String>, new: Option<String> },
+    /// `asm_group_id` changed.
+    AsmGroupIdChanged { old: Option<i32>, new: Option<i32> },
+    /// `send_at` changed.
+    SendAtChanged { old: Option<i64>, new: Option<i64> },
+    /// `ip_pool` changed.
+    IpPoolChanged { old: Option<String>, new: Option<String> },
+    /// `batch_id` changed.
+    BatchIdChanged { old: Option<String>, new: Option<String> },
+}
+
+impl Change {
+    /// Returns the wire name of the changed field.
+    pub fn field(&self) -> &'static str {
+        match *self {
+            Change::RecipientAdded { .. } |
+            Change::RecipientRemoved { .. } => "to",
+            Change::SubstitutionChanged { .. } => "sub",
+            Change::SendEachAtChanged { .. } => "send_each_at",
+            Change::SectionChanged { .. } => "section",
+            Change::CategoryAdded(_) |
+            Change::CategoryRemoved(_) => "category",
+            Change::UniqueArgChanged { .. } => "unique_args",
+            Change::FilterSettingChanged { .. } => "filters",
+            Change::AsmGroupIdChanged { .. } => "asm_group_id",
+            Change::SendAtChanged { .. } => "send_at",
+            Change::IpPoolChanged { .. } => "ip_pool",
+            Change::BatchIdChanged { .. } => "batch_id",
+        }
+    }
+}
+
+/// Writes `value` quoted, or `unset`.
+fn value<T: fmt::Debug>(value: &Option<T>) -> String {
+    match *value {
+        Some(ref x) => format!("{:?}", x),
+        None => "unset".to_owned(),
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Change::RecipientAdded { index, ref email } => write!(f, "to[{}] `{}` added", index, email),
+            Change::RecipientRemoved { index, ref email } => write!(f, "to[{}] `{}` removed", index, email),
+            Change::SubstitutionChanged { ref tag, index, ref email, ref old, ref new } => {
+                write!(f, "sub `{}` of to[{}] `{}` changed from {} to {}", tag, index, email, value(old), value(new))
+            },
+            Change::SendEachAtChanged { index, ref email, ref old, ref new } => {
+                write!(f, "send_each_at of to[{}] `{}` changed from {} to {}", index, email, value(old), value(new))
+            },
+            Change::SectionChanged { ref section, ref old, ref new } => {
+                write!(f, "section `{}` changed from {} to {}", section, value(old), value(new))
+            },
+            Change::CategoryAdded(ref category) => write!(f, "category `{}` added", category),
+            Change::CategoryRemoved(ref category) => write!(f, "category `{}` removed", category),
+            Change::UniqueArgChanged { ref unique_arg, ref old, ref new } => {
+                write!(f, "unique_args `{}` changed from {} to {}", unique_arg, value(old), value(new))
+            },
+            Change::FilterSettingChanged { ref filter, ref setting, ref old, ref new } => {
+                write!(f, "filters `{}` setting `{}` changed from {} to {}", filter, setting, value(old), value(new))
+            },
+            Change::AsmGroupIdChanged { ref old, ref new } => {
+                write!(f, "asm_group_id changed from {} to {}", value(old), value(new))
+            },
+            Change::SendAtChanged { ref old, ref new } => {
+                write!(f, "send_at changed from {} to {}", value(old), value(new))
+            },
+            Change::IpPoolChanged { ref old, ref new } => {
+                write!(f, "ip_pool changed from {} to {}", value(old), value(new))
+            },
+            Change::BatchIdChanged { ref old, ref new } => {
+                write!(f, "batch_id changed from {} to {}", value(old), value(new))
+            },
+        }
+    }
+}
+
+/// Pairs the positions of `old` and `new` holding the same address: the
+/// n-th occurrence of an address in `old` is paired with its n-th occurrence
+/// in `new`, so moving a recipient does not report it as removed and added.
+/// Runs in linear time and space, whatever the size of the To lists.
+fn match_recipients(old: &[String], new: &[String]) -> Vec<(usize, usize)> {
+    let mut positions: HashMap<&str, VecDeque<usize>> = HashMap::with_capacity(new.len());
+    for (j, email) in new.iter().enumerate() {
+        positions.entry(email.as_str()).or_default().push_back(j);
+    }
+
+    old.iter()
+       .enumerate()
+       .filter_map(|(i, email)| {
+           positions.get_mut(email.as_str()).and_then(|js| js.pop_front()).map(|j| (i, j))
+       })
+       .collect()
+}
+
+/// Returns the keys of both maps, sorted, with the value found on each side.
+fn changed_entries<'a, V: PartialEq>(old: &'a HashMap<String, V>, new: &'a HashMap<String, V>)
+    -> Vec<(&'a String, Option<&'a V>, Option<&'a V>)> {
+    let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+
+    keys.into_iter()
+        .map(|key| (key, old.get(key), new.get(key)))
+        .filter(|&(_, a, b)| a != b)
+        .collect()
+}
+
+fn diff_recipients(old: &Header, new: &Header, changes: &mut Vec<Change>) {
+    let pairs = match_recipients(&old.to, &new.to);
+    let mut kept_old = vec![false; old.to.len()];
+    let mut kept_new = vec![false; new.to.len()];
+
+    for &(i, j) in &pairs {
+        kept_old[i] = true;
+        kept_new[j] = true;
+    }
+    for (index, email) in old.to.iter().enumerate().filter(|&(i, _)| !kept_old[i]) {
+        changes.push(Change::RecipientRemoved { index, email: email.clone() });
+    }
+    for (index, email) in new.to.iter().enumerate().filter(|&(j, _)| !kept_new[j]) {
+        changes.push(Change::RecipientAdded { index, email: email.clone() });
+    }
+
+    let tags: BTreeSet<&String> = old.sub.keys().chain(new.sub.keys()).collect();
+    for &(i, j) in &pairs {
+        for tag in &tags {
+            let a = old.sub.get(*tag).and_then(|values| values.get(i)).cloned();
+            let b = new.sub.get(*tag).and_then(|values| values.get(j)).cloned();
+            if a != b {
+                changes.push(Change::SubstitutionChanged { tag: (*tag).clone(), index: j, email: new.to[j].clone(), old: a, new: b });
+            }
+        }
+
+        let a = old.send_each_at.as_ref().and_then(|timestamps| timestamps.get(i)).cloned();
+        let b = new.send_each_at.as_ref().and_then(|timestamps| timestamps.get(j)).cloned();
+        if a != b {
+            changes.push(Change::SendEachAtChanged { index: j, email: new.to[j].clone(), old: a, new: b });
+        }
+    }
+}
+
+pub fn diff(old: &Header, new: &Header) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    diff_recipients(old, new, &mut changes);
+
+    for (section, a, b) in changed_entries(&old.section, &new.section) {
+        changes.push(Change::SectionChanged { section: section.clone(), old: a.cloned(), new: b.cloned() });
+    }
+
+    let old_categories: BTreeSet<&String> = old.categories.iter().collect();
+    let new_categories: BTreeSet<&String> = new.categories.iter().collect();
+    for category in old_categories.difference(&new_categories) {
+        changes.push(Change::CategoryRemoved((*category).clone()));
+    }
+    for category in new_categories.difference(&old_categories) {
+        changes.push(Change::CategoryAdded((*category).clone()));
+    }
+
+    for (unique_arg, a, b) in changed_entries(&old.unique_args, &new.unique_args) {
+        changes.push(Change::UniqueArgChanged { unique_arg: unique_arg.clone(), old: a.cloned(), new: b.cloned() });
+    }
+
+    let filters: BTreeSet<&String> = old.filters.keys().chain(new.filters.keys()).collect();
+    let empty = HashMap::new();
+    for filter in filters {
+        let a = old.filters.get(filter).map_or(&empty, |f| &f.settings);
+        let b = new.filters.get(filter).map_or(&empty, |f| &f.settings);
+        for (setting, a, b) in changed_entries(a, b) {
+            changes.push(Change::FilterSettingChanged { filter: filter.clone(), setting: setting.clone(), old: a.cloned(), new: b.cloned() });
+        }
+    }
+
+    if old.asm_group_id != new.asm_group_id {
+        changes.push(Change::AsmGroupIdChanged { old: old.asm_group_id, new: new.asm_group_id });
+    }
+    if old.send_at != new.send_at {
+        changes.push(Change::SendAtChanged { old: old.send_at, new: new.send_at });
+    }
+    if old.ip_pool != new.ip_pool {
+        changes.push(Change::IpPoolChanged { old: old.ip_pool.clone(), new: new.ip_pool.clone() });
+    }
+    if old.batch_id != new.batch_id {
+        changes.push(Change::BatchIdChanged { old: old.batch_id.clone(), new: new.batch_id.clone() });
+    }
+
+    changes
+}
@@ -0,0 +1,48 @@
+/// How non-ASCII characters are written in the JSON produced by `Header`
+/// and `Filter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    /// Non-ASCII characters are written as raw UTF-8. Only safe on
+    /// connections that negotiated SMTPUTF8.
+    #[default]
+    Utf8,
+    /// Every non-ASCII character is escaped as `\uXXXX`, using a surrogate
+    /// pair for characters outside the Basic Multilingual Plane, so the
+    /// output is 7-bit clean.
+    Ascii,
+}
+
+/// Applies `encoding` to an already serialized JSON value. Non-ASCII
+/// characters can only appear inside JSON strings, so they are escaped
+/// wherever they are found.
+pub fn encode(json: String, encoding: Encoding) -> String {
+    if encoding == Encoding::Utf8 || json.is_ascii() {
+        return json;
+    }
+
+    let mut escaped = String::with_capacity(json.len() + json.len() / 2);
+    let mut units = [0; 2];
+
+    for c in json.chars() {
+        if c.is_ascii() {
+            escaped.push(c);
+        } else {
+            for unit in c.encode_utf16(&mut units).iter() {
+                escaped.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_escapes_astral_characters_as_surrogate_pairs() {
+        assert_eq!("[\"\\u00e1\\ud83d\\udce7a\"]", encode("[\"á📧a\"]".to_string(), Encoding::Ascii));
+        assert_eq!("[\"á📧a\"]", encode("[\"á📧a\"]".to_string(), Encoding::Utf8));
+    }
+}
@@ -0,0 +1,96 @@
+use std::error;
+use std::fmt;
+
+use super::{AddressError, ValidationError};
+
+/// Error returned by `HeaderBuilder::build` when the `Header` would not be
+/// accepted by SendGrid as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SmtpApiError {
+    /// A `to` entry is not an email address.
+    InvalidAddress { index: usize, email: String, reason: AddressError },
+    /// The address of the `bcc` filter is not an email address.
+    InvalidBccAddress { email: String, reason: AddressError },
+    /// A substitution column does not hold one value per recipient.
+    MisalignedSubstitution { tag: String, expected: usize, found: usize },
+    /// `send_each_at` does not hold one timestamp per recipient.
+    MisalignedSendEachAt { expected: usize, found: usize },
+    /// Both `send_at` and `send_each_at` are set.
+    ScheduleConflict,
+    /// A send time is in the past or more than `MAX_SCHEDULE_SECS` ahead.
+    SendTimeOutOfWindow { timestamp: i64, earliest: i64, latest: i64 },
+    /// `send_each_at` is spread over a To list holding no recipient.
+    NoRecipients,
+    /// `send_each_at` is spread at a rate of zero messages per hour.
+    ZeroSpreadRate,
+    /// A timezone is not in the IANA database.
+    UnknownTimezone { index: usize, name: String },
+    /// The serialized header is longer than the allowed number of bytes.
+    TooLarge { len: usize, max: usize },
+    /// A JSON token is too long to fit on a folded header line.
+    LineTooLong { len: usize, max: usize },
+    /// Another rule of the X-SMTPAPI format is broken.
+    Invalid(ValidationError),
+}
+
+impl fmt::Display for SmtpApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SmtpApiError::InvalidAddress { index, ref email, reason } => {
+                write!(f, "to[{}] `{}` is not a valid email address: {}", index, email, reason)
+            },
+            SmtpApiError::InvalidBccAddress { ref email, reason } => {
+                write!(f, "bcc `{}` is not a valid email address: {}", email, reason)
+            },
+            SmtpApiError::MisalignedSubstitution { ref tag, expected, found } => {
+                write!(f, "sub `{}` has {} values but there are {} recipients", tag, found, expected)
+            },
+            SmtpApiError::MisalignedSendEachAt { expected, found } => {
+                write!(f, "send_each_at has {} timestamps but there are {} recipients", found, expected)
+            },
+            SmtpApiError::ScheduleConflict => write!(f, "send_at and send_each_at are both set"),
+            SmtpApiError::SendTimeOutOfWindow { timestamp, earliest, latest } => {
+                write!(f, "send time {} is outside of the scheduling window {}..={}", timestamp, earliest, latest)
+            },
+            SmtpApiError::NoRecipients => write!(f, "send_each_at cannot be spread over an empty to list"),
+            SmtpApiError::ZeroSpreadRate => write!(f, "send_each_at cannot be spread at zero messages per hour"),
+            SmtpApiError::UnknownTimezone { index, ref name } => {
+                write!(f, "timezone[{}] `{}` is not an IANA timezone", index, name)
+            },
+            SmtpApiError::TooLarge { len, max } => {
+                write!(f, "the header is {} bytes long, the limit is {}", len, max)
+            },
+            SmtpApiError::LineTooLong { len, max } => {
+                write!(f, "a header line would be {} bytes long, the limit is {}", len, max)
+            },
+            SmtpApiError::Invalid(ref e) => e.fmt(f),
+        }
+    }
+}
+
+impl error::Error for SmtpApiError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            SmtpApiError::InvalidAddress { ref reason, .. } |
+            SmtpApiError::InvalidBccAddress { ref reason, .. } => Some(reason),
+            SmtpApiError::Invalid(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ValidationError> for SmtpApiError {
+    fn from(e: ValidationError) -> SmtpApiError {
+        match e {
+            ValidationError::EmptyRecipient { index } => {
+                SmtpApiError::InvalidAddress { index, email: String::new(), reason: AddressError::Empty }
+            },
+            ValidationError::SubstitutionLength { tag, expected, found } => {
+                SmtpApiError::MisalignedSubstitution { tag, expected, found }
+            },
+            ValidationError::SendEachAtLength { expected, found } => SmtpApiError::MisalignedSendEachAt { expected, found },
+            ValidationError::ScheduleConflict => SmtpApiError::ScheduleConflict,
+            e => SmtpApiError::Invalid(e),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::io;
+
+use super::SmtpApiError;
+
+/// Name of the header SendGrid reads the JSON value from.
+pub const HEADER_NAME: &str = "X-SMTPAPI";
+
+/// Longest line, CRLF excluded, that RFC 5322 allows in a message header.
+pub const MAX_LINE_LEN: usize = 998;
+
+/// Splits `json` right after every `,`, `:`, `{` and `[` found outside of a
+/// string, the only places where JSON allows whitespace to be inserted.
+fn tokens(json: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in json.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            ',' | ':' | '{' | '[' => {
+                tokens.push(&json[start..i + 1]);
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+
+    if start < json.len() {
+        tokens.push(&json[start..]);
+    }
+
+    tokens
+}
+
+/// Renders `HEADER_NAME: json`, folding with CRLF + space so that no line
+/// exceeds `max_line_len` bytes. A single token longer than a line (e.g. a
+/// huge string value) cannot be folded and is reported as `LineTooLong`.
+pub fn fold(json: &str, max_line_len: usize) -> Result<String, SmtpApiError> {
+    let mut line = String::with_capacity(json.len() + HEADER_NAME.len() + 2);
+    line.push_str(HEADER_NAME);
+    line.push_str(": ");
+
+    let mut len = line.len();
+    let mut empty = true;
+
+    for token in tokens(json) {
+        if !empty && len + token.len() > max_line_len {
+            line.push_str("\r\n ");
+            len = 1;
+        }
+        if len + token.len() > max_line_len {
+            return Err(SmtpApiError::LineTooLong { len: len + token.len(), max: max_line_len });
+        }
+        line.push_str(token);
+        len += token.len();
+        empty = false;
+    }
+
+    Ok(line)
+}
+
+pub fn write<W: io::Write>(w: &mut W, json: &str) -> io::Result<()> {
+    let line = fold(json, MAX_LINE_LEN).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    w.write_all(line.as_bytes())?;
+    w.write_all(b"\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_skip_structural_chars_inside_strings() {
+        assert_eq!(vec!["{", "\"a,b\":", "[", "\"c:\\\"[\",", "1]}"], tokens("{\"a,b\":[\"c:\\\"[\",1]}"));
+    }
+
+    #[test]
+    fn fold_keeps_lines_under_limit() {
+        let json = "{\"to\":[\"foo1@domain.com\",\"foo2@domain.com\",\"foo3@domain.com\"]}";
+        let line = fold(json, 30).unwrap();
+
+        assert_eq!("X-SMTPAPI: {\"to\":[\r\n \"foo1@domain.com\",\r\n \"foo2@domain.com\",\r\n \"foo3@domain.com\"]}", line);
+        assert!(line.split("\r\n").all(|l| l.len() <= 30));
+        assert_eq!(json, line["X-SMTPAPI: ".len()..].replace("\r\n ", ""));
+    }
+
+    #[test]
+    fn fold_rejects_token_longer_than_line() {
+        let json = "{\"to\":[\"foo1@domain.com\",\"a-much-longer-address@domain.com\"]}";
+
+        assert_eq!(Err(SmtpApiError::LineTooLong { len: 37, max: 30 }), fold(json, 30));
+    }
+}
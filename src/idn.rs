@@ -0,0 +1,127 @@
+use super::{AddressError, EmailAddress, Header, SmtpApiError};
+use super::address;
+
+/// How `Header::convert_domains` writes internationalized domain names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdnaForm {
+    /// ASCII-compatible labels, such as `xn--bcher-kva.example`, which do
+    /// not need SMTPUTF8. Local parts are not converted, so an address such
+    /// as `用户@xn--fsqu00a.xn--3lr804guic` still needs it.
+    ALabel,
+    /// Unicode labels, such as `bücher.example`, which need SMTPUTF8.
+    ULabel,
+}
+
+/// Returns `domain` written in `form`. Domain literals are kept as they are.
+pub fn convert(domain: &str, form: IdnaForm) -> Result<String, AddressError> {
+    if domain.starts_with('[') {
+        return Ok(domain.to_owned());
+    }
+
+    match form {
+        IdnaForm::ALabel => ::idna::domain_to_ascii(domain).map_err(|_| AddressError::InvalidIdn),
+        IdnaForm::ULabel => {
+            let (unicode, result) = ::idna::domain_to_unicode(domain);
+            result.map(|_| unicode).map_err(|_| AddressError::InvalidIdn)
+        },
+    }
+}
+
+/// Returns the `Name <address>` or bare address `entry` with its domain
+/// written in `form`. Only the domain is rewritten; the rest of the entry,
+/// display name included, is kept as written.
+fn convert_entry(entry: &str, form: IdnaForm) -> Result<String, AddressError> {
+    let (_, address) = address::parse_mailbox(entry)?;
+    let domain = address.domain();
+
+    // The domain ends the addr-spec, which ends the entry or its `<...>`.
+    let trimmed = entry.trim_end();
+    let end = if trimmed.ends_with('>') { trimmed.len() - 1 } else { trimmed.len() };
+    let start = end - domain.len();
+
+    Ok(format!("{}{}{}", &entry[..start], convert(domain, form)?, &entry[end..]))
+}
+
+pub fn convert_domains(header: &mut Header, form: IdnaForm) -> Result<(), Vec<SmtpApiError>> {
+    let mut errors = Vec::new();
+    let mut to = Vec::with_capacity(header.to.len());
+
+    for (index, email) in header.to.iter().enumerate() {
+        match convert_entry(email, form) {
+            Ok(converted) => to.push(converted),
+            Err(reason) => errors.push(SmtpApiError::InvalidAddress { index, email: email.clone(), reason }),
+        }
+    }
+
+    let bcc = header.filters.get("bcc").and_then(|filter| filter.get("email")).map(|email| {
+        convert_entry(email, form).map_err(|reason| SmtpApiError::InvalidBccAddress { email: email.to_owned(), reason })
+    });
+    let bcc = match bcc {
+        Some(Ok(converted)) => Some(converted),
+        Some(Err(error)) => {
+            errors.push(error);
+            None
+        },
+        None => None,
+    };
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    header.to = to;
+    if let Some(email) = bcc {
+        header.add_filter("bcc".to_owned(), "email".to_owned(), email);
+    }
+
+    Ok(())
+}
+
+impl EmailAddress {
+    /// Returns the address with its domain written in `form`.
+    ///
+    /// Requires the `idna` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{EmailAddress, IdnaForm};
+    ///
+    /// let address: EmailAddress = "info@bücher.example".parse().unwrap();
+    /// assert_eq!("info@xn--bcher-kva.example", address.to_idna(IdnaForm::ALabel).unwrap().to_string());
+    /// ```
+    pub fn to_idna(&self, form: IdnaForm) -> Result<EmailAddress, AddressError> {
+        convert(self.domain(), form).map(|domain| self.with_domain(domain))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_round_trip() {
+        assert_eq!(Ok("xn--bcher-kva.example".to_string()), convert("bücher.example", IdnaForm::ALabel));
+        assert_eq!(Ok("bücher.example".to_string()), convert("xn--bcher-kva.example", IdnaForm::ULabel));
+        assert_eq!(Ok("domain.com".to_string()), convert("Domain.com", IdnaForm::ALabel));
+        assert_eq!(Ok("[192.168.0.1]".to_string()), convert("[192.168.0.1]", IdnaForm::ALabel));
+    }
+
+    #[test]
+    fn invalid_labels() {
+        assert_eq!(Err(AddressError::InvalidIdn), convert("xn--a.example", IdnaForm::ULabel));
+        assert_eq!(Err(AddressError::InvalidIdn), convert("xn--a.example", IdnaForm::ALabel));
+    }
+
+    #[test]
+    fn entries_keep_their_name() {
+        assert_eq!(Ok("=?UTF-8?B?SsO2cmc=?= <jorg@xn--mnchen-3ya.de>".to_string()),
+                   convert_entry("=?UTF-8?B?SsO2cmc=?= <jorg@münchen.de>", IdnaForm::ALabel));
+        assert_eq!(Ok("Jörg <jorg@münchen.de> ".to_string()), convert_entry("Jörg <jorg@xn--mnchen-3ya.de> ", IdnaForm::ULabel));
+        assert_eq!(Ok("\"Doe,  John\"  <\"j@d\"@xn--mnchen-3ya.de>".to_string()),
+                   convert_entry("\"Doe,  John\"  <\"j@d\"@münchen.de>", IdnaForm::ALabel));
+        let ascii = convert_entry("用户@例子.广告", IdnaForm::ALabel).unwrap();
+        assert!(ascii.starts_with("用户@xn--"));
+        assert_eq!(Ok("用户@例子.广告".to_string()), convert_entry(&ascii, IdnaForm::ULabel));
+    }
+}
@@ -1,26 +1,91 @@
 extern crate rustc_serialize;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "time")]
+extern crate time;
+#[cfg(feature = "timezone")]
+extern crate chrono_tz;
+#[cfg(feature = "idna")]
+extern crate idna;
 
 use std::collections::HashMap;
 use std::collections::BTreeMap;
 use std::fmt;
+use std::io;
+use std::str::FromStr;
+use rustc_serialize::{Encodable, Encoder};
 use rustc_serialize::json::{ToJson, Json};
 use std::collections::hash_map::Entry::{Occupied, Vacant};
+use apps::App;
+
+mod address;
+pub mod apps;
+mod builder;
+mod canonical;
+mod decode;
+mod dedup;
+mod diff;
+mod encoding;
+mod error;
+mod fold;
+#[cfg(feature = "idna")]
+mod idn;
+mod mailbox;
+mod merge;
+mod recipient;
+mod schedule;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod split;
+mod spread;
+mod suppress;
+mod timestamp;
+#[cfg(feature = "timezone")]
+mod timezone;
+mod validate;
+
+pub use address::{AddressError, EmailAddress};
+pub use builder::HeaderBuilder;
+pub use decode::ParseError;
+pub use dedup::{DedupPolicy, Occurrence};
+pub use diff::Change;
+pub use encoding::Encoding;
+pub use error::SmtpApiError;
+pub use fold::{HEADER_NAME, MAX_LINE_LEN};
+#[cfg(feature = "idna")]
+pub use idn::IdnaForm;
+pub use mailbox::Mailbox;
+pub use merge::{MergePolicy, MergeStrategy};
+pub use recipient::Recipient;
+pub use schedule::Schedule;
+pub use split::{OversizedRecipient, MAX_RECIPIENTS};
+pub use spread::Spread;
+pub use suppress::{InvalidEntry, Suppressions, SuppressionsError};
+pub use timestamp::{SendTime, MAX_SCHEDULE_SECS};
+pub use validate::{ValidationError, MAX_BATCH_ID_LEN, MAX_CATEGORIES, MAX_CATEGORY_LEN, MAX_UNIQUE_ARGS_LEN};
+
+pub static VERSION: &str = "0.1.3";
+
+/// Returns the entries of `map` sorted by key.
+fn sorted<V>(map: &HashMap<String, V>) -> Vec<(&String, &V)> {
+    let mut entries: Vec<(&String, &V)> = map.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    entries
+}
 
-pub static VERSION: &'static str = "0.1.3";
-
-#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct Filter {
-    settings: HashMap<String, String>
+    settings: HashMap<String, String>,
+    encoding: Encoding
 }
 
+/// Implement Display for Filter as its JSON String reprezentation
 impl fmt::Display for Filter {
     // This trait requires `fmt` with this exact signature.
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // Write strictly the first element into the supplied output
-        // stream: `f`. Returns `fmt::Result` which indicates whether the
-        // operation succeeded or failed. Note that `write!` uses syntax which
-        // is very similar to `println!`.
-        write!(f, "{:?}", self)
+        write!(f, "{}", encoding::encode(self.to_json().to_string(), self.encoding))
     }
 }
 
@@ -42,7 +107,7 @@ impl Filter {
     /// println!("{}", filter.to_string());
     /// ```
     pub fn new() -> Filter {
-        Filter { settings: HashMap::new() }
+        Filter { settings: HashMap::new(), encoding: Encoding::Utf8 }
     }
 
     /// Add settings for a `Filter`.
@@ -63,18 +128,112 @@ impl Filter {
         self
     }
 
-    /// Returns the JSON String reprezentation of `Filter`.
+    /// It sets how non-ASCII characters are written by `to_string()`
+    ///
+    /// When the `Filter` is part of a `Header`, the encoding of the `Header` is used.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Filter, Encoding};
+    ///
+    /// let mut filter = Filter::new();
+    /// filter.add_setting("text/plain", "Désabonnement")
+    ///       .set_encoding(Encoding::Ascii);
+    ///
+    /// assert_eq!("{\"settings\":{\"text/plain\":\"D\\u00e9sabonnement\"}}", filter.to_string());
+    /// ```
+    pub fn set_encoding(&mut self, encoding: Encoding) -> &mut Filter {
+        self.encoding = encoding;
+        self
+    }
+
+    /// It removes a setting
     ///
     /// # Examples
     ///
     /// ```
     /// use smtpapi::{Filter};
     ///
-    /// let filter = Filter::new();
-    /// println!("{}", filter.to_string());
+    /// let mut filter = Filter::new();
+    /// filter.add_setting("enable", "1")
+    ///       .remove_setting("enable");
+    ///
+    /// assert_eq!(None, filter.get("enable"));
+    /// ```
+    pub fn remove_setting(&mut self, setting: &str) -> &mut Filter {
+        self.settings.remove(setting);
+        self
+    }
+
+    /// It removes all the settings
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Filter};
+    ///
+    /// let mut filter = Filter::new();
+    /// filter.add_setting("enable", "1")
+    ///       .clear_settings();
+    ///
+    /// assert_eq!("{}", filter.to_string());
+    /// ```
+    pub fn clear_settings(&mut self) -> &mut Filter {
+        self.settings.clear();
+        self
+    }
+
+    /// Returns the value of a setting
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Filter};
+    ///
+    /// let mut filter = Filter::new();
+    /// filter.add_setting("enable", "1");
+    ///
+    /// assert_eq!(Some("1"), filter.get("enable"));
+    /// assert_eq!(None, filter.get("text/plain"));
+    /// ```
+    pub fn get(&self, setting: &str) -> Option<&str> {
+        self.settings.get(setting).map(|value| value.as_str())
+    }
+
+    /// Returns an iterator over the settings, sorted by name
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Filter};
+    ///
+    /// let mut filter = Filter::new();
+    /// filter.add_setting("text/plain", "bye")
+    ///       .add_setting("enable", "1");
+    ///
+    /// assert_eq!(vec![("enable", "1"), ("text/plain", "bye")], filter.settings().collect::<Vec<_>>());
     /// ```
-    pub fn to_string(&self) -> String {
-        self.to_json().to_string()
+    pub fn settings(&self) -> impl Iterator<Item = (&str, &str)> {
+        sorted(&self.settings).into_iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the encoding used by `to_string()`
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Filter {
+        Filter::new()
+    }
+}
+
+/// Encodes `Filter` using its X-SMTPAPI wire format
+impl Encodable for Filter {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        self.to_json().encode(e)
     }
 }
 
@@ -90,7 +249,7 @@ impl ToJson for Filter {
     }
 }
 
-#[derive(RustcDecodable, RustcEncodable, Debug, Clone)]
+#[derive(Debug, Clone)]
 pub struct Header {
     to: Vec<String>,
     sub: HashMap<String, Vec<String>>,
@@ -101,7 +260,10 @@ pub struct Header {
     asm_group_id: Option<i32>,
     send_at: Option<i64>,
     send_each_at: Option<Vec<i64>>,
-    ip_pool: Option<String>
+    ip_pool: Option<String>,
+    batch_id: Option<String>,
+    encoding: Encoding,
+    sub_default: String
 }
 
 impl Drop for Header {
@@ -160,6 +322,10 @@ impl ToJson for Header {
             d.insert("ip_pool".to_owned(), x.to_json());
         }
 
+        if let Some(ref x) = self.batch_id {
+            d.insert("batch_id".to_owned(), x.to_json());
+        }
+
         Json::Object(d)
     }
 }
@@ -172,7 +338,29 @@ impl fmt::Display for Header {
         // stream: `f`. Returns `fmt::Result` which indicates whether the
         // operation succeeded or failed. Note that `write!` uses syntax which
         // is very similar to `println!`.
-        write!(f, "{:?}", self.to_string())
+        write!(f, "{}", self.to_json_string())
+    }
+}
+
+impl Default for Header {
+    fn default() -> Header {
+        Header::new()
+    }
+}
+
+/// Encodes `Header` using its X-SMTPAPI wire format
+impl Encodable for Header {
+    fn encode<E: Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        self.to_json().encode(e)
+    }
+}
+
+/// Parses an X-SMTPAPI JSON value into a `Header`
+impl FromStr for Header {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Header, ParseError> {
+        Header::from_json_str(s)
     }
 }
 
@@ -197,12 +385,18 @@ impl Header {
                  asm_group_id: None,
                  send_at: None,
                  send_each_at: None,
-                 ip_pool: None
+                 ip_pool: None,
+                 batch_id: None,
+                 encoding: Encoding::Utf8,
+                 sub_default: String::new()
                }
     }
 
     /// Returns the JSON String reprezentation of `Header`.
     ///
+    /// Object keys are always sorted, so two headers holding the same data
+    /// give the same string; arrays keep the order in which values were added.
+    ///
     /// # Examples
     ///
     /// ```
@@ -212,10 +406,18 @@ impl Header {
     /// println!("{}", header.to_json_string());
     /// ```
     pub fn to_json_string(&self) -> String {
-        self.to_json().to_string()
+        encoding::encode(self.to_json().to_string(), self.encoding)
     }
 
-    /// It appends a single email to the To header
+    /// Returns the canonical JSON String reprezentation of `Header`
+    ///
+    /// The canonical form is the output of `to_json_string()` with these
+    /// guarantees, kept stable across crate versions:
+    ///
+    /// * object keys are sorted at every level and empty fields are omitted;
+    /// * `to`, every `sub` column and `send_each_at` keep their positional order;
+    /// * categories, whose order SendGrid ignores, are sorted and deduplicated;
+    /// * non-ASCII characters are written as raw UTF-8, whatever the `Encoding`.
     ///
     /// # Examples
     ///
@@ -223,51 +425,66 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
-    /// header.add_to("email@domain.com");
+    /// header.add_categories(vec!["welcome", "accounts", "welcome"]);
+    ///
+    /// assert_eq!("{\"category\":[\"accounts\",\"welcome\"]}", header.to_canonical_string());
     /// ```
-    pub fn add_to<S>(&mut self, email: S) -> &mut Header where S: Into<String> {
-        self.to.push(email.into());
-        self
+    pub fn to_canonical_string(&self) -> String {
+        canonical::to_string(self)
     }
 
-    /// It appends multiple emails to the To header
+    /// Returns a hash of the canonical form of `Header`
+    ///
+    /// It is the 64-bit FNV-1a hash of `to_canonical_string()`, so it does not
+    /// change between runs, platforms or crate versions, and can be stored to
+    /// deduplicate identical sends.
     ///
     /// # Examples
     ///
     /// ```
     /// use smtpapi::{Header};
     ///
-    /// let mut header = Header::new();
-    /// header.add_tos(vec!["email1@domain.com", "email2@domain.com"]);
+    /// let mut a = Header::new();
+    /// a.add_categories(vec!["welcome", "accounts"]);
+    ///
+    /// let mut b = Header::new();
+    /// b.add_categories(vec!["accounts", "welcome"]);
+    ///
+    /// assert_eq!(a.canonical_hash(), b.canonical_hash());
     /// ```
-    pub fn add_tos<S>(&mut self, emails: Vec<S>) -> &mut Header where S: Into<String> {
-        for email in emails {
-            self.to.push(email.into())
-        }
-
-        self
+    pub fn canonical_hash(&self) -> u64 {
+        canonical::hash(self)
     }
 
-    /// It sets the value of the To header
+    /// It sets how non-ASCII characters are written by `to_json_string()`,
+    /// `to_header_line()` and `write_header()`
+    ///
+    /// Raw UTF-8 is only allowed in a message header when the SMTP connection
+    /// negotiated SMTPUTF8; `Encoding::Ascii` makes the header transport-safe.
     ///
     /// # Examples
     ///
     /// ```
-    /// use smtpapi::{Header};
+    /// use smtpapi::{Header, Encoding};
     ///
     /// let mut header = Header::new();
-    /// header.set_tos(vec!["email1@domain.com", "email2@domain.com"]);
+    /// header.add_category("bienvenue à bord")
+    ///       .set_encoding(Encoding::Ascii);
+    ///
+    /// assert_eq!("{\"category\":[\"bienvenue \\u00e0 bord\"]}", header.to_json_string());
     /// ```
-    pub fn set_tos<S>(&mut self, emails: Vec<S>) -> &mut Header where S: Into<String> {
-        self.to = Vec::new();
-        for email in emails {
-            self.to.push(email.into())
-        }
-
+    pub fn set_encoding(&mut self, encoding: Encoding) -> &mut Header {
+        self.encoding = encoding;
         self
     }
 
-    /// It adds a new substitution to a specific key
+    /// Returns the complete `X-SMTPAPI: ...` header line, without the final CRLF
+    ///
+    /// The JSON value is folded with CRLF followed by a space between JSON
+    /// tokens, so that no line is longer than `MAX_LINE_LEN` bytes. Unfolding
+    /// the line gives back valid JSON. A token that cannot fit on a line,
+    /// such as a very long substitution value, is reported as
+    /// `SmtpApiError::LineTooLong`.
     ///
     /// # Examples
     ///
@@ -275,22 +492,18 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
-    /// header.add_substitution("[name]", "my_name");
+    /// header.add_to("email@domain.com");
+    ///
+    /// assert_eq!("X-SMTPAPI: {\"to\":[\"email@domain.com\"]}", header.to_header_line().unwrap());
     /// ```
-    pub fn add_substitution<S>(&mut self, key: S, sub: S) -> &mut Header where S: Into<String> {
-        match self.sub.entry(key.into()) {
-            Vacant(entry) => {
-                let mut subs = Vec::new();
-                subs.push(sub.into());
-                entry.insert( subs );
-            },
-            Occupied(entry) => entry.into_mut().push(sub.into()),
-        };
-
-        self
+    pub fn to_header_line(&self) -> Result<String, SmtpApiError> {
+        fold::fold(&self.to_json_string(), MAX_LINE_LEN)
     }
 
-    /// It adds a multiple substitutions to a specific key
+    /// It writes the folded `X-SMTPAPI` header line, followed by CRLF, to `w`
+    ///
+    /// Nothing is written, and an `io::ErrorKind::InvalidData` error is
+    /// returned, when the line cannot be folded; see `to_header_line()`.
     ///
     /// # Examples
     ///
@@ -298,77 +511,80 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
-    /// header.add_substitutions("[name]", vec!["my_name_1", "my_name_2"]);
+    /// header.add_to("email@domain.com");
+    ///
+    /// let mut message = Vec::new();
+    /// header.write_header(&mut message).unwrap();
+    /// assert_eq!(b"X-SMTPAPI: {\"to\":[\"email@domain.com\"]}\r\n".to_vec(), message);
     /// ```
-    pub fn add_substitutions<S>(&mut self, key: S, subs: Vec<&str>) -> &mut Header where S: Into<String> {
-        match self.sub.entry(key.into()) {
-            Vacant(entry) => { entry.insert(subs.iter().map(|&x| x.to_owned()).collect::<Vec<String>>()); },
-            Occupied(entry) => { entry.into_mut().extend(subs.iter().map(|&x| x.to_owned()).collect::<Vec<String>>().iter().cloned()); },
-        };
-
-        self
+    pub fn write_header<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        fold::write(w, &self.to_json_string())
     }
 
-    /// It sets the value of the substitutions on the Sub header
+    /// It parses an X-SMTPAPI JSON value back into a `Header`
+    ///
+    /// The wire name `category` is mapped back to the categories and every
+    /// `filters.<name>.settings` object is turned into a `Filter`. Filter
+    /// settings may be given as strings, numbers or booleans.
     ///
     /// # Examples
     ///
     /// ```
     /// use smtpapi::{Header};
-    /// use std::collections::HashMap;
-    ///
-    /// let mut header = Header::new();
-    /// let mut all_subs : HashMap<String, Vec<String>> = HashMap::new();
     ///
-    /// all_subs.insert("-item1-".to_string(), vec!["rust".to_string(), "power".to_string()]);
-    /// all_subs.insert("-item2-".to_string(), vec!["rust".to_string(), "power".to_string()]);
+    /// let header = Header::from_json_str("{\"to\":[\"email@domain.com\"],\"category\":[\"welcome\"]}").unwrap();
+    /// assert_eq!("{\"category\":[\"welcome\"],\"to\":[\"email@domain.com\"]}", header.to_json_string());
     ///
-    /// header.set_substitutions(all_subs);
+    /// assert!(Header::from_json_str("{\"to\":\"email@domain.com\"}").is_err());
     /// ```
-    pub fn set_substitutions(&mut self, subs: HashMap<String, Vec<String>>) -> &mut Header {
-        self.sub = subs;
-
-        self
+    pub fn from_json_str(s: &str) -> Result<Header, ParseError> {
+        decode::header_from_json(Json::from_str(s)?)
     }
 
-    /// It sets the value for a specific section
+    /// It checks the `Header` against the rules SendGrid documents for X-SMTPAPI
+    ///
+    /// Every broken rule is reported, so a single call lists all the problems
+    /// of a mail-merge: substitution columns and `send_each_at` must have one
+    /// entry per recipient, categories must be non-empty and `asm_group_id`
+    /// must be positive, among others.
     ///
     /// # Examples
     ///
     /// ```
-    /// use smtpapi::{Header};
+    /// use smtpapi::{Header, ValidationError};
     ///
     /// let mut header = Header::new();
-    /// header.add_section("-top-", "sample");
+    /// header.add_tos(vec!["email1@domain.com", "email2@domain.com"])
+    ///       .add_substitution("-name-", "John");
+    ///
+    /// let errors = header.validate().unwrap_err();
+    /// assert_eq!(vec![ValidationError::SubstitutionLength { tag: "-name-".to_string(), expected: 2, found: 1 }], errors);
     /// ```
-    pub fn add_section<S>(&mut self, section: S, value: S) -> &mut Header where S: Into<String> {
-        self.section.insert(section.into(), value.into());
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let errors = validate::validate(self);
 
-        self
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
     }
 
-    /// It sets the value for the Section header
+    /// It appends a single email to the To header
     ///
     /// # Examples
     ///
     /// ```
     /// use smtpapi::{Header};
-    /// use std::collections::HashMap;
     ///
     /// let mut header = Header::new();
-    /// let mut sections : HashMap<String, String> = HashMap::new();
-    /// sections.insert("-item1-".to_string(), "value1".to_string());
-    /// sections.insert("-item2-".to_string(), "value2".to_string());
-    ///
-    /// header.set_sections(sections);
+    /// header.add_to("email@domain.com");
     /// ```
-    pub fn set_sections(&mut self, sections: HashMap<String, String>) -> &mut Header {
-        self.section = sections;
-
+    pub fn add_to<S>(&mut self, email: S) -> &mut Header where S: Into<String> {
+        self.to.push(email.into());
         self
     }
 
-    /// It adds a new category to the Category header
+    /// It appends an email with a display name to the To header
+    ///
+    /// The entry is written `Name <email>`, the name being quoted or RFC 2047
+    /// encoded when needed; see `Mailbox`.
     ///
     /// # Examples
     ///
@@ -376,14 +592,17 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
-    /// header.add_category("welcome");
+    /// header.add_named_to("Doe, John", "john@domain.com".parse().unwrap())
+    ///       .add_named_to("Jörg", "jorg@domain.com".parse().unwrap());
+    ///
+    /// assert_eq!(["\"Doe, John\" <john@domain.com>", "=?UTF-8?B?SsO2cmc=?= <jorg@domain.com>"], header.tos());
+    /// assert_eq!(Some("Jörg"), header.recipient(1).unwrap().mailbox().unwrap().name());
     /// ```
-    pub fn add_category<S>(&mut self, category: S) -> &mut Header where S: Into<String> {
-        self.categories.push(category.into());
-        self
+    pub fn add_named_to<S>(&mut self, name: S, email: EmailAddress) -> &mut Header where S: Into<String> {
+        self.add_to(Mailbox::new(name, email))
     }
 
-    /// It adds multiple categories to the Category header
+    /// It appends multiple emails to the To header
     ///
     /// # Examples
     ///
@@ -391,17 +610,17 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
-    /// header.add_categories(vec!["welcome", "new_accounts"]);
+    /// header.add_tos(vec!["email1@domain.com", "email2@domain.com"]);
     /// ```
-    pub fn add_categories<S>(&mut self, categories: Vec<S>) -> &mut Header where S: Into<String> {
-        for category in categories {
-            self.categories.push(category.into());
+    pub fn add_tos<S>(&mut self, emails: Vec<S>) -> &mut Header where S: Into<String> {
+        for email in emails {
+            self.to.push(email.into())
         }
 
         self
     }
 
-    /// It sets the value of the Categories field
+    /// It sets the value of the To header
     ///
     /// # Examples
     ///
@@ -409,18 +628,33 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
-    /// header.set_categories(vec!["welcome", "new_accounts"]);
+    /// header.set_tos(vec!["email1@domain.com", "email2@domain.com"]);
     /// ```
-    pub fn set_categories<S>(&mut self, categories: Vec<S>) -> &mut Header where S: Into<String> {
-        self.categories = Vec::new();
-        for category in categories {
-            self.categories.push(category.into());
+    pub fn set_tos<S>(&mut self, emails: Vec<S>) -> &mut Header where S: Into<String> {
+        self.to = Vec::new();
+        for email in emails {
+            self.to.push(email.into())
         }
 
         self
     }
 
-    /// It sets the value of a specific unique argument
+    /// Returns the `InvalidAddress` errors of `emails`, which would start at
+    /// position `start` of the To list.
+    fn check_addresses(emails: &[String], start: usize) -> Vec<SmtpApiError> {
+        emails.iter()
+              .enumerate()
+              .filter_map(|(i, email)| email.parse::<EmailAddress>().err().map(|reason| {
+                  SmtpApiError::InvalidAddress { index: start + i, email: email.clone(), reason }
+              }))
+              .collect()
+    }
+
+    /// It appends a single email to the To header, if it is a valid address
+    ///
+    /// The email may be a bare address or use the `Name <address>` form, and
+    /// is stored as given, without surrounding whitespace. On error, the
+    /// `index` is the position the email would have had in the To list.
     ///
     /// # Examples
     ///
@@ -428,36 +662,55 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
-    /// header.add_unique_arg("account_id", "123412-121-1212");
+    /// header.try_add_to("John Doe <john@domain.com>").unwrap()
+    ///       .try_add_to(" jane@domain.com ").unwrap();
+    ///
+    /// assert!(header.try_add_to("john@").is_err());
+    /// assert_eq!(["John Doe <john@domain.com>", "jane@domain.com"], header.tos());
     /// ```
-    pub fn add_unique_arg<S>(&mut self, unique_arg: S, value: S) -> &mut Header where S: Into<String> {
-        self.unique_args.insert(unique_arg.into(), value.into());
+    pub fn try_add_to<S>(&mut self, email: S) -> Result<&mut Header, SmtpApiError> where S: Into<String> {
+        let email = email.into().trim().to_owned();
 
-        self
+        if let Some(error) = Header::check_addresses(std::slice::from_ref(&email), self.to.len()).pop() {
+            return Err(error);
+        }
+
+        Ok(self.add_to(email))
     }
 
-    /// It will set the value of the Unique_args header
+    /// It appends multiple emails to the To header, if they are all valid addresses
+    ///
+    /// Surrounding whitespace is removed. Every invalid entry is reported,
+    /// with the position it would have had in the To list, and nothing is
+    /// added.
     ///
     /// # Examples
     ///
     /// ```
-    /// use smtpapi::{Header};
-    /// use std::collections::HashMap;
+    /// use smtpapi::{Header, SmtpApiError};
     ///
     /// let mut header = Header::new();
-    /// let mut unique_args : HashMap<String, String> = HashMap::new();
-    /// unique_args.insert("-arg1-".to_string(), "value1".to_string());
-    /// unique_args.insert("-arg2-".to_string(), "value2".to_string());
+    /// header.add_to("email1@domain.com");
     ///
-    /// header.set_unique_args(unique_args);
+    /// let errors = header.try_add_tos(vec!["email2@domain.com", "Bob Smith bob@domain.com"]).unwrap_err();
+    /// assert_eq!(vec![2], errors.iter().map(|e| match *e { SmtpApiError::InvalidAddress { index, .. } => index, _ => 0 }).collect::<Vec<usize>>());
+    /// assert_eq!(1, header.tos().len());
     /// ```
-    pub fn set_unique_args(&mut self, unique_args: HashMap<String, String>) -> &mut Header {
-        self.unique_args = unique_args;
+    pub fn try_add_tos<S>(&mut self, emails: Vec<S>) -> Result<&mut Header, Vec<SmtpApiError>> where S: Into<String> {
+        let emails: Vec<String> = emails.into_iter().map(|email| email.into().trim().to_owned()).collect();
+        let errors = Header::check_addresses(&emails, self.to.len());
 
-        self
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(self.add_tos(emails))
     }
 
-    /// It will set the specific setting for a filter
+    /// It sets the value of the To header, if all the emails are valid addresses
+    ///
+    /// Surrounding whitespace is removed. Every invalid entry is reported and
+    /// the To header is left untouched.
     ///
     /// # Examples
     ///
@@ -465,41 +718,63 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
-    /// header.add_filter("clicktrack", "enabled", "1")
-    ///       .add_filter("opentrack", "enabled", "1");
+    /// header.try_set_tos(vec!["email1@domain.com", "\"Doe, John\" <john@domain.com>"]).unwrap();
+    ///
+    /// assert_eq!(2, header.try_set_tos(vec!["email1@", "@domain.com"]).unwrap_err().len());
     /// ```
-    pub fn add_filter<S>(&mut self, filter_name: S, setting: S, value: S) -> &mut Header where S: Into<String> {
-        match self.filters.entry(filter_name.into()) {
-            Vacant(entry) => {
-                let mut filter = Filter::new();
-                filter.add_setting(setting, value);
-                entry.insert(filter);
-            },
-            Occupied(entry) => { entry.into_mut().settings.insert(setting.into(), value.into()); },
-        };
+    pub fn try_set_tos<S>(&mut self, emails: Vec<S>) -> Result<&mut Header, Vec<SmtpApiError>> where S: Into<String> {
+        let emails: Vec<String> = emails.into_iter().map(|email| email.into().trim().to_owned()).collect();
+        let errors = Header::check_addresses(&emails, 0);
 
-        self
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(self.set_tos(emails))
     }
 
-    /// It takes in a Filter struct with predetermined settings and sets it for such Filter key
+    /// It rewrites the domain of every To address and of the `bcc` filter
+    /// address as A-labels or U-labels
+    ///
+    /// Only the domain is rewritten: display names and local parts are kept
+    /// as written, so a non-ASCII local part still needs SMTPUTF8 with
+    /// `IdnaForm::ALabel`, and domain literals are left as they are. Every
+    /// entry that is not an address or whose domain is not a valid
+    /// internationalized domain name is reported, and nothing is changed on
+    /// error. Requires the `idna` feature.
     ///
     /// # Examples
     ///
     /// ```
-    /// use smtpapi::{Header, Filter};
+    /// use smtpapi::{Header, IdnaForm};
     ///
     /// let mut header = Header::new();
-    /// let mut filter = Filter::new();
-    /// filter.add_setting("enabled", "1");
+    /// header.add_tos(vec!["info@bücher.example", "Jörg <jorg@münchen.de>"])
+    ///       .add_filter("bcc", "email", "archive@bücher.example");
     ///
-    /// header.set_filter("clicktrack", filter);
+    /// header.convert_domains(IdnaForm::ALabel).unwrap();
+    /// assert_eq!(["info@xn--bcher-kva.example", "Jörg <jorg@xn--mnchen-3ya.de>"], header.tos());
+    ///
+    /// header.convert_domains(IdnaForm::ULabel).unwrap();
+    /// assert_eq!(["info@bücher.example", "Jörg <jorg@münchen.de>"], header.tos());
     /// ```
-    pub fn set_filter<S>(&mut self, filter: S, setting: Filter) -> &mut Header where S: Into<String> {
-        self.filters.insert(filter.into(), setting);
-        self
+    #[cfg(feature = "idna")]
+    pub fn convert_domains(&mut self, form: IdnaForm) -> Result<&mut Header, Vec<SmtpApiError>> {
+        idn::convert_domains(self, form)?;
+        Ok(self)
     }
 
-    /// It sets the value of the IpPool field
+    /// It appends a recipient together with its substitution values
+    ///
+    /// Every substitution column receives exactly one value for the new
+    /// recipient: the one given in `subs`, or the substitution default for
+    /// the tags that are omitted. A tag seen for the first time gets a new
+    /// column, filled with the default for the recipients already present.
+    /// Shorter columns are first padded to the length of the To list, so the
+    /// new values always land at the position of the new recipient. A column
+    /// already longer than the To list is reported as
+    /// `SmtpApiError::MisalignedSubstitution` and the `Header` is left
+    /// unchanged.
     ///
     /// # Examples
     ///
@@ -507,15 +782,1139 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
+    /// header.set_substitution_default("friend")
+    ///       .add_recipient("john@domain.com", vec![("-name-", "John"), ("-city-", "Paris")]).unwrap()
+    ///       .add_recipient("jane@domain.com", vec![("-name-", "Jane")]).unwrap();
     ///
-    /// header.set_ip_pool("newsletter_pool");
+    /// assert_eq!("{\"sub\":{\"-city-\":[\"Paris\",\"friend\"],\"-name-\":[\"John\",\"Jane\"]},\"to\":[\"john@domain.com\",\"jane@domain.com\"]}", header.to_json_string());
+    ///
+    /// header.add_substitutions("-city-", vec!["Rome", "Oslo"]);
+    /// assert!(header.add_recipient("jack@domain.com", vec![("-name-", "Jack")]).is_err());
+    /// assert_eq!(2, header.tos().len());
     /// ```
-    pub fn set_ip_pool<S>(&mut self, name: S) -> &mut Header where S: Into<String> {
-        self.ip_pool = Some(name.into());
+    pub fn add_recipient<S, I, K, V>(&mut self, email: S, subs: I) -> Result<&mut Header, SmtpApiError>
+        where S: Into<String>, I: IntoIterator<Item = (K, V)>, K: Into<String>, V: Into<String> {
+        let count = self.to.len();
+
+        if let Some((tag, values)) = sorted(&self.sub).into_iter().find(|&(_, values)| values.len() > count) {
+            return Err(SmtpApiError::MisalignedSubstitution { tag: tag.clone(), expected: count, found: values.len() });
+        }
+
+        for values in self.sub.values_mut() {
+            values.resize(count, self.sub_default.clone());
+        }
+
+        for (tag, value) in subs {
+            let values = self.sub.entry(tag.into()).or_default();
+            values.resize(count, self.sub_default.clone());
+            values.push(value.into());
+        }
+
+        for values in self.sub.values_mut() {
+            if values.len() == count {
+                values.push(self.sub_default.clone());
+            }
+        }
+
+        self.to.push(email.into());
+        Ok(self)
+    }
+
+    /// It sets the substitution value used by `add_recipient` for omitted tags
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.set_substitution_default("N/A");
+    /// ```
+    pub fn set_substitution_default<S>(&mut self, value: S) -> &mut Header where S: Into<String> {
+        self.sub_default = value.into();
+        self
+    }
+
+    /// Returns the recipient at position `index` of the To list
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_recipient("john@domain.com", vec![("-name-", "John")]).unwrap();
+    ///
+    /// let recipient = header.recipient(0).unwrap();
+    /// assert_eq!("john@domain.com", recipient.email());
+    /// assert_eq!(Some("John"), recipient.substitution("-name-"));
+    /// ```
+    pub fn recipient(&self, index: usize) -> Option<Recipient<'_>> {
+        if index < self.to.len() { Some(Recipient::new(self, index)) } else { None }
+    }
+
+    /// Returns an iterator over the recipients, in To list order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_recipient("john@domain.com", vec![("-name-", "John")]).unwrap()
+    ///       .add_recipient("jane@domain.com", vec![("-name-", "Jane")]).unwrap();
+    ///
+    /// for recipient in header.recipients() {
+    ///     println!("{} {:?}", recipient.email(), recipient.substitutions());
+    /// }
+    /// ```
+    pub fn recipients(&self) -> impl Iterator<Item = Recipient<'_>> {
+        (0..self.to.len()).map(move |index| Recipient::new(self, index))
+    }
+
+    /// It splits the `Header` into several, each with at most `max_recipients` recipients
+    ///
+    /// Every part carries its slice of the To list together with the matching
+    /// positions of each substitution column and of `send_each_at`; all other
+    /// fields (sections, categories, unique arguments, filters, scheduling,
+    /// batch ID, ASM group and IP pool) are copied. SendGrid recommends at most
+    /// `MAX_RECIPIENTS` recipients per header. A `Header` without recipients
+    /// is returned as a single part.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_recipients` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, MAX_RECIPIENTS};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_recipient("john@domain.com", vec![("-name-", "John")]).unwrap()
+    ///       .add_recipient("jane@domain.com", vec![("-name-", "Jane")]).unwrap()
+    ///       .add_recipient("jack@domain.com", vec![("-name-", "Jack")]).unwrap();
+    ///
+    /// let parts = header.split(2);
+    /// assert_eq!(2, parts.len());
+    /// assert_eq!("{\"sub\":{\"-name-\":[\"Jack\"]},\"to\":[\"jack@domain.com\"]}", parts[1].to_json_string());
+    ///
+    /// let parts = header.split(MAX_RECIPIENTS);
+    /// assert_eq!(1, parts.len());
+    /// ```
+    pub fn split(&self, max_recipients: usize) -> Vec<Header> {
+        split::by_count(self, max_recipients)
+    }
+
+    /// It splits the `Header` into several whose `to_json_string()` fits in `max_bytes`
+    ///
+    /// Recipients are packed greedily, in To list order, and each part is
+    /// built the same way as with `split`. When a recipient does not fit in
+    /// `max_bytes` even on its own, every such recipient is reported instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_recipient("john@domain.com", vec![("-name-", "John")]).unwrap()
+    ///       .add_recipient("jane@domain.com", vec![("-name-", "Jane")]).unwrap();
+    ///
+    /// let parts = header.split_by_size(60).unwrap();
+    /// assert_eq!(2, parts.len());
+    /// assert!(parts.iter().all(|part| part.to_json_string().len() <= 60));
+    ///
+    /// let oversized = header.split_by_size(40).unwrap_err();
+    /// assert_eq!(vec![0, 1], oversized.iter().map(|r| r.index).collect::<Vec<usize>>());
+    /// ```
+    pub fn split_by_size(&self, max_bytes: usize) -> Result<Vec<Header>, Vec<OversizedRecipient>> {
+        split::by_size(self, max_bytes)
+    }
+
+    /// It merges `other` into this `Header`, field by field, following `strategy`
+    ///
+    /// This is meant to layer per-send settings over a shared base `Header`.
+    /// With the default strategy, recipients and categories are appended,
+    /// sections and unique arguments are united, filters are merged setting
+    /// by setting and the ASM group, IP pool, batch ID and `send_at` of `other` win when
+    /// they are set. Appended recipients keep their substitution values and
+    /// `send_each_at` timestamps aligned; when only one side has
+    /// `send_each_at`, the recipients of the other side are scheduled at its
+    /// `send_at`. The encoding and substitution default of this `Header` are
+    /// kept.
+    ///
+    /// When recipients are combined, a substitution column or `send_each_at`
+    /// longer than its To list is reported, as is a side with recipients but
+    /// neither `send_each_at` nor `send_at` when the other has
+    /// `send_each_at`. This `Header` is left unchanged on error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, MergePolicy, MergeStrategy};
+    ///
+    /// let mut base = Header::new();
+    /// base.add_category("newsletter")
+    ///     .add_filter("clicktrack", "enable", "1")
+    ///     .set_ip_pool("marketing");
+    ///
+    /// let mut campaign = Header::new();
+    /// campaign.add_to("email@domain.com")
+    ///         .add_category("spring")
+    ///         .set_ip_pool("spring_pool");
+    ///
+    /// base.merge(&campaign, MergeStrategy::default()).unwrap();
+    /// assert_eq!("{\"category\":[\"newsletter\",\"spring\"],\"filters\":{\"clicktrack\":{\"settings\":{\"enable\":\"1\"}}},\"ip_pool\":\"spring_pool\",\"to\":[\"email@domain.com\"]}", base.to_json_string());
+    ///
+    /// let strategy = MergeStrategy { categories: MergePolicy::Keep, ..MergeStrategy::default() };
+    /// base.merge(&campaign, strategy).unwrap();
+    /// assert_eq!(["newsletter", "spring"], base.categories());
+    /// ```
+    pub fn merge(&mut self, other: &Header, strategy: MergeStrategy) -> Result<&mut Header, SmtpApiError> {
+        merge::merge(self, other, strategy)?;
+        Ok(self)
+    }
+
+    /// Returns the field-level changes that turn this `Header` into `other`
+    ///
+    /// Recipients are matched by address, so a recipient added in the middle
+    /// of the To list is reported once and the others are compared with their
+    /// own substitution values and `send_each_at` timestamps. Categories are
+    /// compared as sets. An empty list means both headers send the same mail.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Change};
+    ///
+    /// let mut old = Header::new();
+    /// old.add_recipient("john@domain.com", vec![("-name-", "John")]).unwrap();
+    ///
+    /// let mut new = old.clone();
+    /// new.add_recipient("jane@domain.com", vec![("-name-", "Jane")]).unwrap()
+    ///    .set_ip_pool("newsletter_pool");
+    ///
+    /// let changes = old.diff(&new);
+    /// assert_eq!(Change::RecipientAdded { index: 1, email: "jane@domain.com".to_string() }, changes[0]);
+    /// assert_eq!("ip_pool changed from unset to \"newsletter_pool\"", changes[1].to_string());
+    /// ```
+    pub fn diff(&self, other: &Header) -> Vec<Change> {
+        diff::diff(self, other)
+    }
+
+    /// It adds a new substitution to a specific key
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_substitution("[name]", "my_name");
+    /// ```
+    pub fn add_substitution<S>(&mut self, key: S, sub: S) -> &mut Header where S: Into<String> {
+        match self.sub.entry(key.into()) {
+            Vacant(entry) => { entry.insert(vec![sub.into()]); },
+            Occupied(entry) => entry.into_mut().push(sub.into()),
+        };
+
+        self
+    }
+
+    /// It adds a multiple substitutions to a specific key
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_substitutions("[name]", vec!["my_name_1", "my_name_2"]);
+    /// ```
+    pub fn add_substitutions<S>(&mut self, key: S, subs: Vec<&str>) -> &mut Header where S: Into<String> {
+        match self.sub.entry(key.into()) {
+            Vacant(entry) => { entry.insert(subs.iter().map(|&x| x.to_owned()).collect::<Vec<String>>()); },
+            Occupied(entry) => { entry.into_mut().extend(subs.iter().map(|&x| x.to_owned()).collect::<Vec<String>>().iter().cloned()); },
+        };
+
+        self
+    }
+
+    /// It sets the value of the substitutions on the Sub header
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut header = Header::new();
+    /// let mut all_subs : HashMap<String, Vec<String>> = HashMap::new();
+    ///
+    /// all_subs.insert("-item1-".to_string(), vec!["rust".to_string(), "power".to_string()]);
+    /// all_subs.insert("-item2-".to_string(), vec!["rust".to_string(), "power".to_string()]);
+    ///
+    /// header.set_substitutions(all_subs);
+    /// ```
+    pub fn set_substitutions(&mut self, subs: HashMap<String, Vec<String>>) -> &mut Header {
+        self.sub = subs;
+
+        self
+    }
+
+    /// It sets the value for a specific section
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_section("-top-", "sample");
+    /// ```
+    pub fn add_section<S>(&mut self, section: S, value: S) -> &mut Header where S: Into<String> {
+        self.section.insert(section.into(), value.into());
+
+        self
+    }
+
+    /// It sets the value for the Section header
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut header = Header::new();
+    /// let mut sections : HashMap<String, String> = HashMap::new();
+    /// sections.insert("-item1-".to_string(), "value1".to_string());
+    /// sections.insert("-item2-".to_string(), "value2".to_string());
+    ///
+    /// header.set_sections(sections);
+    /// ```
+    pub fn set_sections(&mut self, sections: HashMap<String, String>) -> &mut Header {
+        self.section = sections;
+
+        self
+    }
+
+    /// It adds a new category to the Category header
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_category("welcome");
+    /// ```
+    pub fn add_category<S>(&mut self, category: S) -> &mut Header where S: Into<String> {
+        self.categories.push(category.into());
+        self
+    }
+
+    /// It adds multiple categories to the Category header
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_categories(vec!["welcome", "new_accounts"]);
+    /// ```
+    pub fn add_categories<S>(&mut self, categories: Vec<S>) -> &mut Header where S: Into<String> {
+        for category in categories {
+            self.categories.push(category.into());
+        }
+
+        self
+    }
+
+    /// It sets the value of the Categories field
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.set_categories(vec!["welcome", "new_accounts"]);
+    /// ```
+    pub fn set_categories<S>(&mut self, categories: Vec<S>) -> &mut Header where S: Into<String> {
+        self.categories = Vec::new();
+        for category in categories {
+            self.categories.push(category.into());
+        }
+
+        self
+    }
+
+    /// It sets the value of a specific unique argument
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_unique_arg("account_id", "123412-121-1212");
+    /// ```
+    pub fn add_unique_arg<S>(&mut self, unique_arg: S, value: S) -> &mut Header where S: Into<String> {
+        self.unique_args.insert(unique_arg.into(), value.into());
+
+        self
+    }
+
+    /// It will set the value of the Unique_args header
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    /// use std::collections::HashMap;
+    ///
+    /// let mut header = Header::new();
+    /// let mut unique_args : HashMap<String, String> = HashMap::new();
+    /// unique_args.insert("-arg1-".to_string(), "value1".to_string());
+    /// unique_args.insert("-arg2-".to_string(), "value2".to_string());
+    ///
+    /// header.set_unique_args(unique_args);
+    /// ```
+    pub fn set_unique_args(&mut self, unique_args: HashMap<String, String>) -> &mut Header {
+        self.unique_args = unique_args;
+
+        self
+    }
+
+    /// It will set the specific setting for a filter
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_filter("clicktrack", "enabled", "1")
+    ///       .add_filter("opentrack", "enabled", "1");
+    /// ```
+    pub fn add_filter<S>(&mut self, filter_name: S, setting: S, value: S) -> &mut Header where S: Into<String> {
+        match self.filters.entry(filter_name.into()) {
+            Vacant(entry) => {
+                let mut filter = Filter::new();
+                filter.add_setting(setting, value);
+                entry.insert(filter);
+            },
+            Occupied(entry) => { entry.into_mut().settings.insert(setting.into(), value.into()); },
+        };
+
+        self
+    }
+
+    /// It takes in a Filter struct with predetermined settings and sets it for such Filter key
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Filter};
+    ///
+    /// let mut header = Header::new();
+    /// let mut filter = Filter::new();
+    /// filter.add_setting("enabled", "1");
+    ///
+    /// header.set_filter("clicktrack", filter);
+    /// ```
+    pub fn set_filter<S>(&mut self, filter: S, setting: Filter) -> &mut Header where S: Into<String> {
+        self.filters.insert(filter.into(), setting);
+        self
+    }
+
+    /// It sets the settings of a SendGrid app, replacing any previous ones
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    /// use smtpapi::apps::{Bcc, GAnalytics};
+    ///
+    /// let mut header = Header::new();
+    ///
+    /// header.set_app(Bcc::new().email("archive@domain.com"))
+    ///       .set_app(GAnalytics::new().utm_source("newsletter").utm_campaign("spring"));
+    /// ```
+    pub fn set_app<A>(&mut self, app: &A) -> &mut Header where A: App + ?Sized {
+        self.filters.insert(app.name().to_owned(), app.to_filter());
+        self
+    }
+
+    /// It sets the value of the IpPool field
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    ///
+    /// header.set_ip_pool("newsletter_pool");
+    /// ```
+    pub fn set_ip_pool<S>(&mut self, name: S) -> &mut Header where S: Into<String> {
+        self.ip_pool = Some(name.into());
+        self
+    }
+
+    /// It sets the batch ID, which groups scheduled sends so that they can be
+    /// paused or cancelled together
+    ///
+    /// The ID is created beforehand with the SendGrid mail batch API.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Schedule};
+    ///
+    /// let mut header = Header::new();
+    ///
+    /// header.set_schedule(Schedule::At(1453213937))
+    ///       .set_batch_id("HkJ5yLYULb7Rj8GKSx7u025ouWVlMgAi");
+    /// ```
+    pub fn set_batch_id<S>(&mut self, batch_id: S) -> &mut Header where S: Into<String> {
+        self.batch_id = Some(batch_id.into());
+        self
+    }
+
+    /// It will set the value of the ASMGroupID field
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    ///
+    /// header.set_asm_group_id(1221);
+    /// ```
+    pub fn set_asm_group_id(&mut self, asm_group_id: i32) -> &mut Header {
+        self.asm_group_id = Some(asm_group_id);
+        self
+    }
+
+    /// It takes in a timestamp which determines when the email will be sent
+    ///
+    /// Any `send_each_at` is silently dropped. Use `set_schedule`, which
+    /// replaces the whole schedule explicitly, or `try_set_schedule`, which
+    /// refuses to replace another schedule.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #![allow(deprecated)]
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    ///
+    /// header.set_send_at(1453213937);
+    /// ```
+    #[deprecated(note = "use `set_schedule(Schedule::At(..))` or `try_set_schedule`")]
+    pub fn set_send_at(&mut self, send_at: i64) -> &mut Header {
+        self.set_schedule(Schedule::At(send_at))
+    }
+
+    /// It takes in a timestamp and pushes it into a list. Must match length of To emails
+    ///
+    /// Any `send_at` is silently dropped, as `send_each_at` replaces it; use
+    /// `try_add_send_each_at` to be told about the conflict instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    ///
+    /// header.add_send_each_at(1453213937)
+    ///       .add_send_each_at(1453213939);
+    /// ```
+    pub fn add_send_each_at(&mut self, send_at: i64) -> &mut Header {
+        if self.send_each_at.is_none() {
+            self.send_each_at = Some(Vec::new());
+        }
+
+        if let Some(ref mut x) = self.send_each_at {
+            x.push(send_at);
+        }
+        self.send_at = None;
+        self
+    }
+
+    /// It pushes a timestamp into `send_each_at`, unless `send_at` is set
+    ///
+    /// Unlike `add_send_each_at`, this reports `SmtpApiError::ScheduleConflict`
+    /// and leaves the `Header` untouched when `send_at` is set.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Schedule, SmtpApiError};
+    ///
+    /// let mut header = Header::new();
+    /// header.try_add_send_each_at(1453213937).unwrap()
+    ///       .try_add_send_each_at(1453213939).unwrap();
+    /// assert_eq!(Schedule::EachAt(vec![1453213937, 1453213939]), header.schedule());
+    ///
+    /// header.set_schedule(Schedule::At(1453213937));
+    /// assert_eq!(SmtpApiError::ScheduleConflict, header.try_add_send_each_at(1453213939).unwrap_err());
+    /// assert_eq!(Schedule::At(1453213937), header.schedule());
+    /// ```
+    pub fn try_add_send_each_at(&mut self, send_at: i64) -> Result<&mut Header, SmtpApiError> {
+        if self.send_at.is_some() {
+            return Err(SmtpApiError::ScheduleConflict);
+        }
+
+        Ok(self.add_send_each_at(send_at))
+    }
+
+    /// It takes an array of timestamps. Must match length of To emails
+    ///
+    /// Any `send_at` is silently dropped. Use `set_schedule`, which
+    /// replaces the whole schedule explicitly, or `try_set_schedule`, which
+    /// refuses to replace another schedule.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #![allow(deprecated)]
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    ///
+    /// header.set_send_each_at(vec![1453213939, 1453213932, 1453213933]);
+    /// ```
+    #[deprecated(note = "use `set_schedule(Schedule::EachAt(..))` or `try_set_schedule`")]
+    pub fn set_send_each_at(&mut self, send_each_at: Vec<i64>) -> &mut Header {
+        self.set_schedule(Schedule::EachAt(send_each_at))
+    }
+
+    /// It sets when the email will be sent, from a typed point in time
+    ///
+    /// Unlike `set_send_at`, the time is checked against the scheduling window
+    /// of SendGrid: it must not be in the past nor more than
+    /// `MAX_SCHEDULE_SECS` ahead, otherwise nothing is changed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let mut header = Header::new();
+    /// let in_an_hour = SystemTime::now() + Duration::from_secs(3600);
+    ///
+    /// header.set_send_at_time(in_an_hour).unwrap();
+    /// assert!(header.set_send_at_time(SystemTime::now() - Duration::from_secs(3600)).is_err());
+    /// ```
+    pub fn set_send_at_time<T>(&mut self, send_at: T) -> Result<&mut Header, SmtpApiError> where T: SendTime {
+        let timestamp = timestamp::check(send_at.to_timestamp(), timestamp::now())?;
+        Ok(self.set_schedule(Schedule::At(timestamp)))
+    }
+
+    /// It pushes a typed point in time into `send_each_at`, checked like `set_send_at_time`
+    /// and failing with `SmtpApiError::ScheduleConflict` like `try_add_send_each_at`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, SmtpApiError};
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let mut header = Header::new();
+    /// let now = SystemTime::now();
+    ///
+    /// header.add_send_each_at_time(now + Duration::from_secs(60)).unwrap()
+    ///       .add_send_each_at_time(now + Duration::from_secs(120)).unwrap();
+    ///
+    /// header.set_send_at_time(now + Duration::from_secs(60)).unwrap();
+    /// assert_eq!(Err(SmtpApiError::ScheduleConflict), header.add_send_each_at_time(now + Duration::from_secs(120)).map(|_| ()));
+    /// ```
+    pub fn add_send_each_at_time<T>(&mut self, send_at: T) -> Result<&mut Header, SmtpApiError> where T: SendTime {
+        let timestamp = timestamp::check(send_at.to_timestamp(), timestamp::now())?;
+        self.try_add_send_each_at(timestamp)
+    }
+
+    /// It sets `send_each_at` from typed points in time, checked like `set_send_at_time`
+    ///
+    /// Nothing is changed when one of the times is outside of the window.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let mut header = Header::new();
+    /// let now = SystemTime::now();
+    ///
+    /// header.set_send_each_at_times(vec![now + Duration::from_secs(60), now + Duration::from_secs(120)]).unwrap();
+    /// ```
+    pub fn set_send_each_at_times<T>(&mut self, send_each_at: Vec<T>) -> Result<&mut Header, SmtpApiError> where T: SendTime {
+        let now = timestamp::now();
+        let timestamps = send_each_at.iter()
+                                     .map(|time| timestamp::check(time.to_timestamp(), now))
+                                     .collect::<Result<Vec<i64>, SmtpApiError>>()?;
+        Ok(self.set_schedule(Schedule::EachAt(timestamps)))
+    }
+
+    /// It sets `send_each_at` so that the recipients of the To list are
+    /// spread from `start` at the rate given by `spread`
+    ///
+    /// Recipients are sent in To list order. Every time is checked like
+    /// `set_send_at_time`, so a spread longer than `MAX_SCHEDULE_SECS` is
+    /// refused, and nothing is changed on error. An empty To list fails with
+    /// `SmtpApiError::NoRecipients` and `Spread::per_hour(0)` with
+    /// `SmtpApiError::ZeroSpreadRate`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Spread};
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let mut header = Header::new();
+    /// let start = SystemTime::now() + Duration::from_secs(60);
+    ///
+    /// header.add_tos(vec!["email1@domain.com", "email2@domain.com", "email3@domain.com"])
+    ///       .spread_send_each_at(start, &Spread::per_hour(2))
+    ///       .unwrap();
+    ///
+    /// let send_each_at = header.send_each_at().unwrap();
+    /// assert_eq!(vec![1800, 3600], vec![send_each_at[1] - send_each_at[0], send_each_at[2] - send_each_at[0]]);
+    /// ```
+    pub fn spread_send_each_at<T>(&mut self, start: T, spread: &Spread) -> Result<&mut Header, SmtpApiError> where T: SendTime {
+        if self.to.is_empty() {
+            return Err(SmtpApiError::NoRecipients);
+        }
+        if spread.is_zero_rate() {
+            return Err(SmtpApiError::ZeroSpreadRate);
+        }
+        let now = timestamp::now();
+        let timestamps = spread.timestamps(start.to_timestamp(), self.to.len())
+                               .into_iter()
+                               .map(|timestamp| timestamp::check(timestamp, now))
+                               .collect::<Result<Vec<i64>, SmtpApiError>>()?;
+        Ok(self.set_schedule(Schedule::EachAt(timestamps)))
+    }
+
+    /// It sets `send_each_at` so that each recipient gets the email at the
+    /// wall-clock time `local` of its own timezone
+    ///
+    /// `timezones` holds one IANA name, such as `Europe/Paris`, per recipient
+    /// of the To list. A time skipped when clocks are turned forward is moved
+    /// forward by the length of the gap, and a time that happens twice when
+    /// clocks are turned back resolves to its first occurrence. The times are
+    /// checked like `set_send_at_time`, and nothing is changed on error.
+    /// Requires the `timezone` feature; the timezone database is bundled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate chrono;
+    /// # extern crate smtpapi;
+    /// use chrono::{DateTime, Duration, NaiveTime, Utc};
+    /// use smtpapi::{Header};
+    /// use std::time::SystemTime;
+    ///
+    /// # fn main() {
+    /// let now: DateTime<Utc> = SystemTime::now().into();
+    /// let nine_am = (now + Duration::days(2)).date_naive().and_time(NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+    ///
+    /// let mut header = Header::new();
+    /// header.add_tos(vec!["email1@domain.com", "email2@domain.com"])
+    ///       .set_send_each_at_local(nine_am, &["Europe/Paris", "America/New_York"])
+    ///       .unwrap();
+    ///
+    /// let send_each_at = header.send_each_at().unwrap();
+    /// assert_eq!(6 * 3600, send_each_at[1] - send_each_at[0]);
+    /// # }
+    /// ```
+    #[cfg(feature = "timezone")]
+    pub fn set_send_each_at_local<S>(&mut self, local: chrono::NaiveDateTime, timezones: &[S]) -> Result<&mut Header, SmtpApiError>
+        where S: AsRef<str> {
+        if timezones.len() != self.to.len() {
+            return Err(SmtpApiError::MisalignedSendEachAt { expected: self.to.len(), found: timezones.len() });
+        }
+
+        let now = timestamp::now();
+        let timestamps = timezone::timestamps(&local, timezones)?
+                                  .into_iter()
+                                  .map(|timestamp| timestamp::check(timestamp, now))
+                                  .collect::<Result<Vec<i64>, SmtpApiError>>()?;
+        Ok(self.set_schedule(Schedule::EachAt(timestamps)))
+    }
+
+    /// It sets when the message is sent, replacing any previous `send_at` or `send_each_at`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Schedule};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_tos(vec!["email1@domain.com", "email2@domain.com"])
+    ///       .set_schedule(Schedule::EachAt(vec![1453213937, 1453213939]));
+    ///
+    /// assert_eq!("{\"send_each_at\":[1453213937,1453213939],\"to\":[\"email1@domain.com\",\"email2@domain.com\"]}", header.to_json_string());
+    /// ```
+    pub fn set_schedule(&mut self, schedule: Schedule) -> &mut Header {
+        schedule::set(self, schedule);
+        self
+    }
+
+    /// It sets when the message is sent, unless another schedule is already set
+    ///
+    /// Unlike `set_schedule`, which replaces the previous schedule, this reports
+    /// `SmtpApiError::ScheduleConflict` and leaves the `Header` untouched when
+    /// it holds a different schedule. Setting the same schedule twice is
+    /// accepted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Schedule, SmtpApiError};
+    ///
+    /// let mut header = Header::new();
+    /// header.try_set_schedule(Schedule::At(1453213937)).unwrap();
+    /// header.try_set_schedule(Schedule::At(1453213937)).unwrap();
+    ///
+    /// let error = header.try_set_schedule(Schedule::EachAt(vec![1453213939])).unwrap_err();
+    /// assert_eq!(SmtpApiError::ScheduleConflict, error);
+    /// assert_eq!(Schedule::At(1453213937), header.schedule());
+    /// ```
+    pub fn try_set_schedule(&mut self, schedule: Schedule) -> Result<&mut Header, SmtpApiError> {
+        schedule::try_set(self, schedule)?;
+        Ok(self)
+    }
+
+    /// Keeps only the recipients for which `keep(index, email)` is true,
+    /// removing the matching positions of every substitution column and of
+    /// `send_each_at`. Entries past the end of the To list are left alone.
+    fn retain_recipients<F>(&mut self, mut keep: F) where F: FnMut(usize, &str) -> bool {
+        let kept: Vec<bool> = self.to.iter().enumerate().map(|(i, email)| keep(i, email)).collect();
+        let retain = |index: usize| kept.get(index).cloned().unwrap_or(true);
+
+        fn retain_positions<T, P: Fn(usize) -> bool>(values: &mut Vec<T>, retain: P) {
+            let mut index = 0;
+            values.retain(|_| { index += 1; retain(index - 1) });
+        }
+
+        retain_positions(&mut self.to, retain);
+        for values in self.sub.values_mut() {
+            retain_positions(values, retain);
+        }
+        if let Some(ref mut timestamps) = self.send_each_at {
+            retain_positions(timestamps, retain);
+        }
+    }
+
+    /// It removes the recipient at position `index`, along with its
+    /// substitution values and `send_each_at` timestamp
+    ///
+    /// Nothing is changed when `index` is out of bounds of the To list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_recipient("john@domain.com", vec![("-name-", "John")]).unwrap()
+    ///       .add_recipient("jane@domain.com", vec![("-name-", "Jane")]).unwrap()
+    ///       .remove_recipient(0);
+    ///
+    /// assert_eq!("{\"sub\":{\"-name-\":[\"Jane\"]},\"to\":[\"jane@domain.com\"]}", header.to_json_string());
+    ///
+    /// header.remove_recipient(5);
+    /// assert_eq!(["jane@domain.com"], header.tos());
+    /// ```
+    pub fn remove_recipient(&mut self, index: usize) -> &mut Header {
+        self.retain_recipients(|i, _| i != index);
+        self
+    }
+
+    /// It removes every occurrence of `email` from the To header, along with
+    /// the matching substitution values and `send_each_at` timestamps
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_tos(vec!["email1@domain.com", "email2@domain.com"])
+    ///       .remove_to("email1@domain.com");
+    ///
+    /// assert_eq!(["email2@domain.com"], header.tos());
+    /// ```
+    pub fn remove_to(&mut self, email: &str) -> &mut Header {
+        self.retain_recipients(|_, to| to != email);
+        self
+    }
+
+    /// It removes duplicated recipients, along with their substitution
+    /// values and `send_each_at` timestamps
+    ///
+    /// Addresses are compared without their display name, with the domain
+    /// lowercased and, when `policy.fold_local_part` is set, the local part
+    /// too. The first or last occurrence is kept, per `policy.keep`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, DedupPolicy, Occurrence};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_recipient("john@domain.com", vec![("-name-", "John")]).unwrap()
+    ///       .add_recipient("jane@domain.com", vec![("-name-", "Jane")]).unwrap()
+    ///       .add_recipient("John Doe <john@DOMAIN.com>", vec![("-name-", "Johnny")]).unwrap()
+    ///       .dedup_recipients(DedupPolicy { keep: Occurrence::Last, ..DedupPolicy::default() });
+    ///
+    /// assert_eq!(["jane@domain.com", "John Doe <john@DOMAIN.com>"], header.tos());
+    /// assert_eq!("{\"sub\":{\"-name-\":[\"Jane\",\"Johnny\"]},\"to\":[\"jane@domain.com\",\"John Doe <john@DOMAIN.com>\"]}", header.to_json_string());
+    /// ```
+    pub fn dedup_recipients(&mut self, policy: DedupPolicy) -> &mut Header {
+        let kept = dedup::kept(&self.to, policy);
+        self.retain_recipients(|i, _| kept[i]);
+        self
+    }
+
+    /// Removes the recipients on a suppression list, along with their
+    /// substitution values and `send_each_at` timestamps, and returns them
+    /// as they were written in the To list
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Suppressions};
+    ///
+    /// let mut suppressions = Suppressions::new();
+    /// suppressions.try_add("*@bounced.example").unwrap();
+    ///
+    /// let mut header = Header::new();
+    /// header.add_recipient("john@domain.com", vec![("-name-", "John")]).unwrap()
+    ///       .add_recipient("Jane <jane@bounced.example>", vec![("-name-", "Jane")]).unwrap();
+    ///
+    /// assert_eq!(vec!["Jane <jane@bounced.example>"], header.apply_suppressions(&suppressions));
+    /// assert_eq!("{\"sub\":{\"-name-\":[\"John\"]},\"to\":[\"john@domain.com\"]}", header.to_json_string());
+    /// ```
+    pub fn apply_suppressions(&mut self, suppressions: &Suppressions) -> Vec<String> {
+        let mut removed = Vec::new();
+
+        self.retain_recipients(|_, email| {
+            let suppressed = suppressions.contains(email);
+            if suppressed {
+                removed.push(email.to_owned());
+            }
+            !suppressed
+        });
+
+        removed
+    }
+
+    /// It removes all recipients, along with their substitution values and
+    /// `send_each_at` timestamps
+    ///
+    /// Substitution columns left empty and `send_each_at` are removed, so
+    /// the recipient fields serialize as in a new `Header`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_recipient("john@domain.com", vec![("-name-", "John")]).unwrap()
+    ///       .add_send_each_at(1453213937)
+    ///       .clear_tos();
+    ///
+    /// assert_eq!("{}", header.to_json_string());
+    /// ```
+    pub fn clear_tos(&mut self) -> &mut Header {
+        self.retain_recipients(|_, _| false);
+        self.sub.retain(|_, values| !values.is_empty());
+        if self.send_each_at.as_ref().is_some_and(|timestamps| timestamps.is_empty()) {
+            self.send_each_at = None;
+        }
+        self
+    }
+
+    /// It removes a substitution key and its values
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_substitution("[name]", "my_name")
+    ///       .remove_substitution("[name]");
+    ///
+    /// assert_eq!("{}", header.to_json_string());
+    /// ```
+    pub fn remove_substitution(&mut self, key: &str) -> &mut Header {
+        self.sub.remove(key);
+        self
+    }
+
+    /// It removes all the substitutions
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_substitution("[name]", "my_name")
+    ///       .clear_substitutions();
+    /// ```
+    pub fn clear_substitutions(&mut self) -> &mut Header {
+        self.sub.clear();
+        self
+    }
+
+    /// It removes a section
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_section("-top-", "sample")
+    ///       .remove_section("-top-");
+    /// ```
+    pub fn remove_section(&mut self, section: &str) -> &mut Header {
+        self.section.remove(section);
+        self
+    }
+
+    /// It removes all the sections
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_section("-top-", "sample")
+    ///       .clear_sections();
+    /// ```
+    pub fn clear_sections(&mut self) -> &mut Header {
+        self.section.clear();
+        self
+    }
+
+    /// It removes every occurrence of a category
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_categories(vec!["welcome", "new_accounts"])
+    ///       .remove_category("welcome");
+    ///
+    /// assert_eq!(["new_accounts"], header.categories());
+    /// ```
+    pub fn remove_category(&mut self, category: &str) -> &mut Header {
+        self.categories.retain(|c| c != category);
+        self
+    }
+
+    /// It removes all the categories
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_category("welcome")
+    ///       .clear_categories();
+    /// ```
+    pub fn clear_categories(&mut self) -> &mut Header {
+        self.categories.clear();
+        self
+    }
+
+    /// It removes a unique argument
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_unique_arg("account_id", "123412-121-1212")
+    ///       .remove_unique_arg("account_id");
+    /// ```
+    pub fn remove_unique_arg(&mut self, unique_arg: &str) -> &mut Header {
+        self.unique_args.remove(unique_arg);
+        self
+    }
+
+    /// It removes all the unique arguments
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_unique_arg("account_id", "123412-121-1212")
+    ///       .clear_unique_args();
+    /// ```
+    pub fn clear_unique_args(&mut self) -> &mut Header {
+        self.unique_args.clear();
+        self
+    }
+
+    /// It removes a filter and all its settings
+    ///
+    /// To remove a single setting, use `Filter::remove_setting` through `filter_mut`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_filter("clicktrack", "enable", "1")
+    ///       .remove_filter("clicktrack");
+    /// ```
+    pub fn remove_filter(&mut self, filter: &str) -> &mut Header {
+        self.filters.remove(filter);
+        self
+    }
+
+    /// It removes all the filters
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_filter("clicktrack", "enable", "1")
+    ///       .clear_filters();
+    /// ```
+    pub fn clear_filters(&mut self) -> &mut Header {
+        self.filters.clear();
         self
     }
 
-    /// It will set the value of the ASMGroupID field
+    /// Returns the settings of a specific filter, for modification
     ///
     /// # Examples
     ///
@@ -523,15 +1922,33 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
+    /// header.add_filter("footer", "enable", "1")
+    ///       .add_filter("footer", "text/plain", "bye");
     ///
-    /// header.set_asm_group_id(1221);
+    /// header.filter_mut("footer").unwrap().remove_setting("text/plain");
+    /// assert_eq!("{\"filters\":{\"footer\":{\"settings\":{\"enable\":\"1\"}}}}", header.to_json_string());
     /// ```
-    pub fn set_asm_group_id(&mut self, asm_group_id: i32) -> &mut Header {
-        self.asm_group_id = Some(asm_group_id);
+    pub fn filter_mut(&mut self, filter: &str) -> Option<&mut Filter> {
+        self.filters.get_mut(filter)
+    }
+
+    /// It unsets the IpPool field
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.set_ip_pool("newsletter_pool")
+    ///       .unset_ip_pool();
+    /// ```
+    pub fn unset_ip_pool(&mut self) -> &mut Header {
+        self.ip_pool = None;
         self
     }
 
-    /// It takes in a timestamp which determines when the email will be sent
+    /// It unsets the batch ID
     ///
     /// # Examples
     ///
@@ -539,16 +1956,63 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
+    /// header.set_batch_id("HkJ5yLYULb7Rj8GKSx7u025ouWVlMgAi")
+    ///       .unset_batch_id();
+    /// ```
+    pub fn unset_batch_id(&mut self) -> &mut Header {
+        self.batch_id = None;
+        self
+    }
+
+    /// It unsets the ASMGroupID field
+    ///
+    /// # Examples
     ///
-    /// header.set_send_at(1453213937);
     /// ```
-    pub fn set_send_at(&mut self, send_at: i64) -> &mut Header {
-        self.send_at = Some(send_at);
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.set_asm_group_id(1221)
+    ///       .unset_asm_group_id();
+    /// ```
+    pub fn unset_asm_group_id(&mut self) -> &mut Header {
+        self.asm_group_id = None;
+        self
+    }
+
+    /// It unsets the SendAt field
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Schedule};
+    ///
+    /// let mut header = Header::new();
+    /// header.set_schedule(Schedule::At(1453213937))
+    ///       .unset_send_at();
+    /// ```
+    pub fn unset_send_at(&mut self) -> &mut Header {
+        self.send_at = None;
+        self
+    }
+
+    /// It unsets the SendEachAt field
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_send_each_at(1453213937)
+    ///       .unset_send_each_at();
+    /// ```
+    pub fn unset_send_each_at(&mut self) -> &mut Header {
         self.send_each_at = None;
         self
     }
 
-    /// It takes in a timestamp and pushes it into a list. Must match length of To emails
+    /// Returns the emails of the To header
     ///
     /// # Examples
     ///
@@ -556,23 +2020,31 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
+    /// header.add_tos(vec!["email1@domain.com", "email2@domain.com"]);
     ///
-    /// header.add_send_each_at(1453213937)
-    ///       .add_send_each_at(1453213939);
+    /// assert_eq!(["email1@domain.com", "email2@domain.com"], header.tos());
     /// ```
-    pub fn add_send_each_at(&mut self, send_at: i64) -> &mut Header {
-        if !self.send_each_at.is_some() {
-            self.send_each_at = Some(Vec::new());
-        }
+    pub fn tos(&self) -> &[String] {
+        &self.to
+    }
 
-        if let Some(ref mut x) = self.send_each_at {
-            x.push(send_at);
-        }
-        self.send_at = None;
-        self
+    /// Returns the values of the substitution `key`, one per recipient
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_substitutions("[name]", vec!["my_name_1", "my_name_2"]);
+    ///
+    /// assert_eq!(Some(&["my_name_1".to_string(), "my_name_2".to_string()][..]), header.substitution("[name]"));
+    /// ```
+    pub fn substitution(&self, key: &str) -> Option<&[String]> {
+        self.sub.get(key).map(|values| values.as_slice())
     }
 
-    /// It takes an array of timestamps. Must match length of To emails
+    /// Returns an iterator over the substitution columns, sorted by key
     ///
     /// # Examples
     ///
@@ -580,22 +2052,270 @@ impl Header {
     /// use smtpapi::{Header};
     ///
     /// let mut header = Header::new();
+    /// header.add_substitution("[name]", "my_name");
     ///
-    /// header.set_send_each_at(vec![1453213939, 1453213932, 1453213933]);
+    /// for (key, values) in header.substitutions() {
+    ///     println!("{}: {:?}", key, values);
+    /// }
     /// ```
-    pub fn set_send_each_at(&mut self, send_each_at: Vec<i64>) -> &mut Header {
-        self.send_each_at = Some(send_each_at);
-        self.send_at = None;
-        self
+    pub fn substitutions(&self) -> impl Iterator<Item = (&str, &[String])> {
+        sorted(&self.sub).into_iter().map(|(k, v)| (k.as_str(), v.as_slice()))
+    }
+
+    /// Returns the value of a specific section
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_section("-top-", "sample");
+    ///
+    /// assert_eq!(Some("sample"), header.section("-top-"));
+    /// ```
+    pub fn section(&self, section: &str) -> Option<&str> {
+        self.section.get(section).map(|value| value.as_str())
+    }
+
+    /// Returns an iterator over the sections, sorted by name
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_section("-top-", "sample");
+    ///
+    /// assert_eq!(vec![("-top-", "sample")], header.sections().collect::<Vec<_>>());
+    /// ```
+    pub fn sections(&self) -> impl Iterator<Item = (&str, &str)> {
+        sorted(&self.section).into_iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the categories, in the order they were added
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_categories(vec!["welcome", "new_accounts"]);
+    ///
+    /// assert_eq!(["welcome", "new_accounts"], header.categories());
+    /// ```
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
+
+    /// Returns the value of a specific unique argument
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_unique_arg("account_id", "123412-121-1212");
+    ///
+    /// assert_eq!(Some("123412-121-1212"), header.unique_arg("account_id"));
+    /// ```
+    pub fn unique_arg(&self, unique_arg: &str) -> Option<&str> {
+        self.unique_args.get(unique_arg).map(|value| value.as_str())
+    }
+
+    /// Returns an iterator over the unique arguments, sorted by name
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_unique_arg("account_id", "123412-121-1212");
+    ///
+    /// assert_eq!(vec![("account_id", "123412-121-1212")], header.unique_args().collect::<Vec<_>>());
+    /// ```
+    pub fn unique_args(&self) -> impl Iterator<Item = (&str, &str)> {
+        sorted(&self.unique_args).into_iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Returns the settings of a specific filter
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_filter("clicktrack", "enable", "1");
+    ///
+    /// assert_eq!(Some("1"), header.filter("clicktrack").and_then(|filter| filter.get("enable")));
+    /// ```
+    pub fn filter(&self, filter: &str) -> Option<&Filter> {
+        self.filters.get(filter)
+    }
+
+    /// Returns an iterator over the filters, sorted by name
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.add_filter("clicktrack", "enable", "1")
+    ///       .add_filter("opentrack", "enable", "1");
+    ///
+    /// assert_eq!(vec!["clicktrack", "opentrack"], header.filters().map(|(name, _)| name).collect::<Vec<_>>());
+    /// ```
+    pub fn filters(&self) -> impl Iterator<Item = (&str, &Filter)> {
+        sorted(&self.filters).into_iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Returns the value of the IpPool field
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.set_ip_pool("newsletter_pool");
+    ///
+    /// assert_eq!(Some("newsletter_pool"), header.ip_pool());
+    /// ```
+    pub fn ip_pool(&self) -> Option<&str> {
+        self.ip_pool.as_deref()
+    }
+
+    /// Returns the batch ID
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.set_batch_id("HkJ5yLYULb7Rj8GKSx7u025ouWVlMgAi");
+    ///
+    /// assert_eq!(Some("HkJ5yLYULb7Rj8GKSx7u025ouWVlMgAi"), header.batch_id());
+    /// ```
+    pub fn batch_id(&self) -> Option<&str> {
+        self.batch_id.as_deref()
+    }
+
+    /// Returns the value of the ASMGroupID field
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header};
+    ///
+    /// let mut header = Header::new();
+    /// header.set_asm_group_id(1221);
+    ///
+    /// assert_eq!(Some(1221), header.asm_group_id());
+    /// ```
+    pub fn asm_group_id(&self) -> Option<i32> {
+        self.asm_group_id
+    }
+
+    /// Returns the timestamp at which the email will be sent
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Schedule};
+    ///
+    /// let mut header = Header::new();
+    /// header.set_schedule(Schedule::At(1453213937));
+    ///
+    /// assert_eq!(Some(1453213937), header.send_at());
+    /// ```
+    pub fn send_at(&self) -> Option<i64> {
+        self.send_at
+    }
+
+    /// Returns the timestamps at which the email will be sent to each recipient
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Schedule};
+    ///
+    /// let mut header = Header::new();
+    /// header.set_schedule(Schedule::EachAt(vec![1453213939, 1453213932]));
+    ///
+    /// assert_eq!(Some(&[1453213939, 1453213932][..]), header.send_each_at());
+    /// ```
+    pub fn send_each_at(&self) -> Option<&[i64]> {
+        self.send_each_at.as_deref()
+    }
+
+    /// Returns the time at which the email will be sent, as `T`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Schedule};
+    /// use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    ///
+    /// let mut header = Header::new();
+    /// header.set_schedule(Schedule::At(1453213937));
+    ///
+    /// assert_eq!(Some(UNIX_EPOCH + Duration::from_secs(1453213937)), header.send_at_time::<SystemTime>());
+    /// ```
+    pub fn send_at_time<T>(&self) -> Option<T> where T: SendTime {
+        self.send_at.and_then(T::from_timestamp)
+    }
+
+    /// Returns the times at which the email will be sent to each recipient, as `T`
+    ///
+    /// `None` is returned when `send_each_at` is not set or holds a timestamp
+    /// that `T` cannot represent.
+    pub fn send_each_at_times<T>(&self) -> Option<Vec<T>> where T: SendTime {
+        self.send_each_at.as_ref().and_then(|timestamps| timestamps.iter().map(|&x| T::from_timestamp(x)).collect())
+    }
+
+    /// Returns when the message is sent
+    ///
+    /// A parsed `Header` may hold both `send_at` and `send_each_at`, which
+    /// `validate()` reports; `send_each_at` is returned in that case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Header, Schedule};
+    ///
+    /// let mut header = Header::new();
+    /// assert_eq!(Schedule::Immediate, header.schedule());
+    ///
+    /// header.set_schedule(Schedule::At(1453213937));
+    /// assert_eq!(Schedule::At(1453213937), header.schedule());
+    /// ```
+    pub fn schedule(&self) -> Schedule {
+        schedule::get(self)
+    }
+
+    /// Returns the encoding used by `to_json_string()`
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Returns the substitution value used by `add_recipient` for omitted tags
+    pub fn substitution_default(&self) -> &str {
+        &self.sub_default
     }
 }
 
 #[cfg(test)]
 mod tests {
-    extern crate time;
-
     use super::*;
     use std::collections::HashMap;
+    use std::time::SystemTime;
     use super::rustc_serialize::json::ToJson;
 
     #[test]
@@ -645,19 +2365,19 @@ mod tests {
 
         header.add_substitution("-top-", "foobar1");
 
-        assert_eq!(true, header.sub.contains_key("-top-"));
+        assert!(header.sub.contains_key("-top-"));
         assert_eq!("{\"sub\":{\"-top-\":[\"foobar1\"]}}", header.to_json_string());
 
         header.add_substitution("-top-", "foobar2");
         assert_eq!("{\"sub\":{\"-top-\":[\"foobar1\",\"foobar2\"]}}", header.to_json_string());
 
         header.add_substitutions("-ztags-", tags);
-        assert_eq!(true, header.sub.contains_key("-ztags-"));
+        assert!(header.sub.contains_key("-ztags-"));
         assert_eq!("{\"sub\":{\"-top-\":[\"foobar1\",\"foobar2\"],\"-ztags-\":[\"rust\",\"power\"]}}", header.to_json_string());
 
         header.set_substitutions(all_subs);
-        assert_eq!(true, header.sub.contains_key("-item1-"));
-        assert_eq!(true, header.sub.contains_key("-item2-"));
+        assert!(header.sub.contains_key("-item1-"));
+        assert!(header.sub.contains_key("-item2-"));
         assert_eq!("{\"sub\":{\"-item1-\":[\"rust\",\"power\"],\"-item2-\":[\"rust\",\"power\"]}}", header.to_json_string());
 
     }
@@ -671,15 +2391,15 @@ mod tests {
 
         header.add_section("-top1-", "foobar1");
 
-        assert_eq!(true, header.section.contains_key("-top1-"));
+        assert!(header.section.contains_key("-top1-"));
         assert_eq!("{\"section\":{\"-top1-\":\"foobar1\"}}", header.to_json_string());
 
         header.add_section("-top1-", "foobar2");
         assert_eq!("{\"section\":{\"-top1-\":\"foobar2\"}}", header.to_json_string());
 
         header.set_sections(sections);
-        assert_eq!(true, header.section.contains_key("-item1-"));
-        assert_eq!(true, header.section.contains_key("-item2-"));
+        assert!(header.section.contains_key("-item1-"));
+        assert!(header.section.contains_key("-item2-"));
         assert_eq!("{\"section\":{\"-item1-\":\"value1\",\"-item2-\":\"value2\"}}", header.to_json_string());
     }
 
@@ -692,15 +2412,15 @@ mod tests {
 
         header.add_unique_arg("-arg1-", "foobar1");
 
-        assert_eq!(true, header.unique_args.contains_key("-arg1-"));
+        assert!(header.unique_args.contains_key("-arg1-"));
         assert_eq!("{\"unique_args\":{\"-arg1-\":\"foobar1\"}}", header.to_json_string());
 
         header.add_unique_arg("-arg1-", "foobar2");
         assert_eq!("{\"unique_args\":{\"-arg1-\":\"foobar2\"}}", header.to_json_string());
 
         header.set_unique_args(unique_args);
-        assert_eq!(true, header.unique_args.contains_key("-arg1-"));
-        assert_eq!(true, header.unique_args.contains_key("-arg2-"));
+        assert!(header.unique_args.contains_key("-arg1-"));
+        assert!(header.unique_args.contains_key("-arg2-"));
         assert_eq!("{\"unique_args\":{\"-arg1-\":\"value1\",\"-arg2-\":\"value2\"}}", header.to_json_string());
     }
 
@@ -709,7 +2429,7 @@ mod tests {
         let mut header = Header::new();
         header.add_filter("clicktrack", "enabled", "1");
 
-        assert_eq!(true, header.filters.contains_key("clicktrack"));
+        assert!(header.filters.contains_key("clicktrack"));
         assert_eq!("{\"filters\":{\"clicktrack\":{\"settings\":{\"enabled\":\"1\"}}}}", header.to_json().to_string());
     }
 
@@ -721,8 +2441,8 @@ mod tests {
               .add_filter("footer", "enabled", "1")
               .add_filter("footer", "text/html", "<strong>boo</strong>");
 
-        assert_eq!(true, header.filters.contains_key("clicktrack"));
-        assert_eq!(true, header.filters.contains_key("footer"));
+        assert!(header.filters.contains_key("clicktrack"));
+        assert!(header.filters.contains_key("footer"));
         assert_eq!("{\"filters\":{\"clicktrack\":{\"settings\":{\"enabled\":\"1\"}},\"footer\":{\"settings\":{\"enabled\":\"1\",\"text/html\":\"<strong>boo</strong>\"}}}}", header.to_json().to_string());
     }
 
@@ -733,7 +2453,7 @@ mod tests {
         filter.add_setting("enabled", "1");
         header.set_filter("clicktrack", filter);
 
-        assert_eq!(true, header.filters.contains_key("clicktrack"));
+        assert!(header.filters.contains_key("clicktrack"));
         assert_eq!("{\"filters\":{\"clicktrack\":{\"settings\":{\"enabled\":\"1\"}}}}", header.to_json_string());
     }
 
@@ -785,29 +2505,29 @@ mod tests {
     }
 
     #[test]
+    #[allow(deprecated)]
     fn header_with_send_at_to_json_string() {
-        let x = time::now_utc().to_timespec().sec as i64;
-        let mut timestamps = Vec::new();
-        timestamps.push(x);
-        timestamps.push(x);
+        let x = SystemTime::now().to_timestamp();
+        let timestamps = vec![x, x];
 
         let mut header = Header::new();
         header.set_send_each_at(timestamps);
-        assert_eq!(header.send_each_at.is_some(), true);
+        assert!(header.send_each_at.is_some());
 
         header.set_send_at(x);
 
         let mut s = "{\"send_at\":".to_string();
         s.push_str(&format!("{:?}", x));
-        s.push_str("}");
+        s.push('}');
 
         assert_eq!(s, header.to_json_string());
-        assert_eq!(header.send_each_at.is_none(), true);
+        assert!(header.send_each_at.is_none());
     }
 
     #[test]
+    #[allow(deprecated)]
     fn header_with_send_each_at_to_json_string() {
-        let x = time::now_utc().to_timespec().sec as i64;
+        let x = SystemTime::now().to_timestamp();
         let y = x + 50;
         let z = x + 40;
         let timestamps = vec![x, y];
@@ -815,20 +2535,20 @@ mod tests {
         let mut header = Header::new();
 
         header.set_send_at(x);
-        assert_eq!(header.send_at.is_some(), true);
+        assert!(header.send_at.is_some());
 
         header.set_send_each_at(timestamps)
               .add_send_each_at(z);
 
         let mut s = "{\"send_each_at\":[".to_string();
         s.push_str(&format!("{:?}", x));
-        s.push_str(",");
+        s.push(',');
         s.push_str(&format!("{:?}", y));
-        s.push_str(",");
+        s.push(',');
         s.push_str(&format!("{:?}", z));
         s.push_str("]}");
 
         assert_eq!(s, header.to_json_string());
-        assert_eq!(header.send_at.is_none(), true);
+        assert!(header.send_at.is_none());
     }
 }
@@ -0,0 +1,274 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::address::{self, AddressError, EmailAddress};
+
+/// The longest run of bytes encoded into one RFC 2047 encoded-word, so that
+/// `=?UTF-8?B?...?=` stays within 75 characters.
+const MAX_ENCODED_WORD_BYTES: usize = 45;
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// An email address with an optional display name, written `Name <address>`.
+///
+/// The display name is written as is when it only holds letters, digits
+/// and spaces, as a quoted string when it holds other ASCII characters
+/// such as commas or quotes, and as RFC 2047 encoded-words when it holds
+/// non-ASCII characters. Parsing undoes the quoting and the encoding.
+///
+/// # Examples
+///
+/// ```
+/// use smtpapi::{Mailbox, EmailAddress};
+///
+/// let address: EmailAddress = "john@domain.com".parse().unwrap();
+///
+/// assert_eq!("John Doe <john@domain.com>", Mailbox::new("John Doe", address.clone()).to_string());
+/// assert_eq!("\"Doe, John\" <john@domain.com>", Mailbox::new("Doe, John", address.clone()).to_string());
+/// assert_eq!("=?UTF-8?B?SsO2cmc=?= <john@domain.com>", Mailbox::new("Jörg", address).to_string());
+///
+/// let mailbox: Mailbox = "=?UTF-8?B?SsO2cmc=?= <john@domain.com>".parse().unwrap();
+/// assert_eq!(Some("Jörg"), mailbox.name());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Mailbox {
+    name: Option<String>,
+    address: EmailAddress
+}
+
+impl Mailbox {
+    /// Constructs a new `Mailbox` with a display name.
+    pub fn new<S>(name: S, address: EmailAddress) -> Mailbox where S: Into<String> {
+        let name = name.into();
+        Mailbox { name: if name.trim().is_empty() { None } else { Some(name) }, address }
+    }
+
+    /// Returns the display name, decoded.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the email address.
+    pub fn address(&self) -> &EmailAddress {
+        &self.address
+    }
+}
+
+impl From<EmailAddress> for Mailbox {
+    fn from(address: EmailAddress) -> Mailbox {
+        Mailbox { name: None, address }
+    }
+}
+
+impl From<Mailbox> for String {
+    fn from(mailbox: Mailbox) -> String {
+        mailbox.to_string()
+    }
+}
+
+impl fmt::Display for Mailbox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name {
+            Some(ref name) => write!(f, "{} <{}>", encode_name(name), self.address),
+            None => write!(f, "{}", self.address),
+        }
+    }
+}
+
+/// Parses a bare address or the `Name <address>` form, unquoting and
+/// decoding the display name
+impl FromStr for Mailbox {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Mailbox, AddressError> {
+        let (name, address) = address::parse_mailbox(s)?;
+        Ok(Mailbox { name: name.map(decode_name), address })
+    }
+}
+
+fn is_plain(name: &str) -> bool {
+    name.chars().all(|c| c.is_ascii_alphanumeric() || c == ' ') &&
+        !name.starts_with(' ') && !name.ends_with(' ')
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut out = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+
+    out
+}
+
+fn unbase64(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut n = 0u32;
+    let mut bits = 0;
+
+    for c in text.bytes().filter(|&c| c != b'=') {
+        n = n << 6 | BASE64.iter().position(|&x| x == c)? as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((n >> bits) as u8);
+        }
+    }
+
+    Some(out)
+}
+
+fn unquote_printable(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut bytes = text.bytes();
+
+    while let Some(b) = bytes.next() {
+        match b {
+            b'_' => out.push(b' '),
+            b'=' => {
+                let hex = [bytes.next()?, bytes.next()?];
+                out.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            },
+            b => out.push(b),
+        }
+    }
+
+    Some(out)
+}
+
+/// Splits `name` into runs of at most `MAX_ENCODED_WORD_BYTES` bytes, on
+/// character boundaries, each written as a base64 encoded-word.
+fn encode_words(name: &str) -> String {
+    let mut words = Vec::new();
+    let mut start = 0;
+
+    for (i, c) in name.char_indices() {
+        if i + c.len_utf8() - start > MAX_ENCODED_WORD_BYTES {
+            words.push(&name[start..i]);
+            start = i;
+        }
+    }
+    words.push(&name[start..]);
+
+    words.iter().map(|word| format!("=?UTF-8?B?{}?=", base64(word.as_bytes()))).collect::<Vec<String>>().join(" ")
+}
+
+fn encode_name(name: &str) -> String {
+    if is_plain(name) {
+        name.to_owned()
+    } else if name.chars().all(|c| c == ' ' || c.is_ascii_graphic()) {
+        format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        encode_words(name)
+    }
+}
+
+/// Decodes one `=?charset?encoding?text?=` encoded-word, for the UTF-8 and
+/// US-ASCII charsets.
+fn decode_word(word: &str) -> Option<String> {
+    if !word.starts_with("=?") || !word.ends_with("?=") || word.len() < 4 {
+        return None;
+    }
+
+    let mut parts = word[2..word.len() - 2].splitn(3, '?');
+    let charset = parts.next()?.to_ascii_lowercase();
+    let encoding = parts.next()?.to_ascii_lowercase();
+    let text = parts.next()?;
+
+    if charset != "utf-8" && charset != "us-ascii" {
+        return None;
+    }
+
+    let bytes = match encoding.as_str() {
+        "b" => unbase64(text)?,
+        "q" => unquote_printable(text)?,
+        _ => return None,
+    };
+
+    String::from_utf8(bytes).ok()
+}
+
+fn decode_name(name: &str) -> String {
+    if name.starts_with('"') {
+        let mut out = String::new();
+        let mut chars = name[1..name.len() - 1].chars();
+        while let Some(c) = chars.next() {
+            out.push(if c == '\\' { chars.next().unwrap_or(c) } else { c });
+        }
+        return out;
+    }
+
+    // Whitespace between two encoded-words is not part of the text.
+    let mut out = String::new();
+    let mut previous_encoded = false;
+    for word in name.split_whitespace() {
+        match decode_word(word) {
+            Some(text) => {
+                if !out.is_empty() && !previous_encoded {
+                    out.push(' ');
+                }
+                out.push_str(&text);
+                previous_encoded = true;
+            },
+            None => {
+                if !out.is_empty() {
+                    out.push(' ');
+                }
+                out.push_str(word);
+                previous_encoded = false;
+            },
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mailbox(name: &str) -> Mailbox {
+        Mailbox::new(name, "john@domain.com".parse().unwrap())
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        for s in &["", "f", "fo", "foo", "foob", "fooba", "foobar", "Jörg 天破活殺"] {
+            assert_eq!(Some(s.as_bytes().to_vec()), unbase64(&base64(s.as_bytes())));
+        }
+        assert_eq!("Zm9vYmE=", base64(b"fooba"));
+    }
+
+    #[test]
+    fn names_are_quoted_or_encoded() {
+        assert_eq!("John Doe <john@domain.com>", mailbox("John Doe").to_string());
+        assert_eq!("\"J. \\\"Johnny\\\" Doe\" <john@domain.com>", mailbox("J. \"Johnny\" Doe").to_string());
+        assert_eq!("john@domain.com", mailbox(" ").to_string());
+
+        let long = "天破活殺".repeat(10);
+        let encoded = mailbox(&long).to_string();
+        assert!(encoded.split(' ').all(|word| word.len() <= 75));
+        assert_eq!(Some(long.as_str()), encoded.parse::<Mailbox>().unwrap().name());
+    }
+
+    #[test]
+    fn names_are_decoded() {
+        for name in &["John Doe", "Doe, John", "J. \"Johnny\" Doe", "back\\slash", "Jörg Müller", "天破活殺"] {
+            assert_eq!(Some(*name), mailbox(name).to_string().parse::<Mailbox>().unwrap().name());
+        }
+
+        let mailbox: Mailbox = "=?utf-8?q?J=C3=B6rg_M?= =?UTF-8?B?w7xsbGVy?= <john@domain.com>".parse().unwrap();
+        assert_eq!(Some("Jörg Müller"), mailbox.name());
+        let mailbox: Mailbox = "Dr. =?UTF-8?B?SsO2cmc=?= <john@domain.com>".parse().unwrap();
+        assert_eq!(Some("Dr. Jörg"), mailbox.name());
+        let mailbox: Mailbox = "john@domain.com".parse().unwrap();
+        assert_eq!(None, mailbox.name());
+    }
+}
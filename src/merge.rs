@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::{sorted, Header, SmtpApiError};
+
+/// How `Header::merge` combines one field of the other `Header` into this one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// The field of this `Header` is left untouched.
+    Keep,
+    /// The field of the other `Header` replaces this one, when it is set.
+    Replace,
+    /// Lists are appended, maps are united with the other `Header` winning
+    /// on conflicting keys, and filters are merged setting by setting.
+    /// Single values behave as with `Replace`.
+    Combine,
+}
+
+/// The `MergePolicy` applied by `Header::merge` to each group of fields.
+///
+/// The default layers a per-send `Header` over a base one: recipients,
+/// categories, sections and unique arguments are combined, filters are
+/// merged setting by setting and scalars are overridden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergeStrategy {
+    /// `to`, together with the substitution columns and `send_each_at`.
+    pub recipients: MergePolicy,
+    /// `section`.
+    pub sections: MergePolicy,
+    /// `category`.
+    pub categories: MergePolicy,
+    /// `unique_args`.
+    pub unique_args: MergePolicy,
+    /// `filters`.
+    pub filters: MergePolicy,
+    /// `asm_group_id`, `send_at`, `ip_pool` and `batch_id`.
+    pub scalars: MergePolicy,
+}
+
+impl MergeStrategy {
+    /// Returns a strategy applying `policy` to every field.
+    pub fn all(policy: MergePolicy) -> MergeStrategy {
+        MergeStrategy {
+            recipients: policy,
+            sections: policy,
+            categories: policy,
+            unique_args: policy,
+            filters: policy,
+            scalars: policy
+        }
+    }
+}
+
+impl Default for MergeStrategy {
+    fn default() -> MergeStrategy {
+        MergeStrategy::all(MergePolicy::Combine)
+    }
+}
+
+fn merge_list<T: Clone>(ours: &mut Vec<T>, theirs: &[T], policy: MergePolicy) {
+    match policy {
+        MergePolicy::Keep => {},
+        MergePolicy::Replace => if !theirs.is_empty() { *ours = theirs.to_vec() },
+        MergePolicy::Combine => ours.extend_from_slice(theirs),
+    }
+}
+
+fn merge_map<K, V>(ours: &mut HashMap<K, V>, theirs: &HashMap<K, V>, policy: MergePolicy)
+    where K: Eq + Hash + Clone, V: Clone {
+    match policy {
+        MergePolicy::Keep => {},
+        MergePolicy::Replace => if !theirs.is_empty() { *ours = theirs.clone() },
+        MergePolicy::Combine => ours.extend(theirs.iter().map(|(k, v)| (k.clone(), v.clone()))),
+    }
+}
+
+fn merge_scalar<T: Clone>(ours: &mut Option<T>, theirs: &Option<T>, policy: MergePolicy) -> bool {
+    if policy == MergePolicy::Keep || theirs.is_none() {
+        return false;
+    }
+
+    *ours = theirs.clone();
+    true
+}
+
+/// Returns the `send_each_at` of `header`, or an error when it does not hold
+/// one timestamp per recipient.
+fn aligned_send_each_at(header: &Header) -> Result<Option<&Vec<i64>>, SmtpApiError> {
+    match header.send_each_at {
+        Some(ref timestamps) if timestamps.len() != header.to.len() => {
+            Err(SmtpApiError::MisalignedSendEachAt { expected: header.to.len(), found: timestamps.len() })
+        },
+        ref timestamps => Ok(timestamps.as_ref()),
+    }
+}
+
+/// Returns an error for the first substitution column of `header` that is
+/// longer than its To list.
+fn check_sub(header: &Header) -> Result<(), SmtpApiError> {
+    let count = header.to.len();
+
+    match sorted(&header.sub).into_iter().find(|&(_, values)| values.len() > count) {
+        Some((tag, values)) => Err(SmtpApiError::MisalignedSubstitution { tag: tag.clone(), expected: count, found: values.len() }),
+        None => Ok(()),
+    }
+}
+
+/// Returns the timestamps of the `count` recipients of one side: its
+/// `send_each_at`, or its `send_at` repeated for every recipient.
+fn side_timestamps(timestamps: Option<&Vec<i64>>, send_at: Option<i64>, count: usize) -> Option<Vec<i64>> {
+    match (timestamps, send_at) {
+        (Some(timestamps), _) => Some(timestamps.clone()),
+        (None, _) if count == 0 => Some(Vec::new()),
+        (None, Some(send_at)) => Some(vec![send_at; count]),
+        (None, None) => None,
+    }
+}
+
+/// Appends the recipients of `other`, keeping every substitution column
+/// aligned with the To list: shorter columns are padded to the length of
+/// their own To list with the substitution default of `header` before being
+/// joined, and a tag missing on one side is filled with that default.
+///
+/// When only one side has `send_each_at`, the recipients of the other side
+/// are scheduled at its `send_at`. Nothing is changed, and an error is
+/// returned, when a column or `send_each_at` is longer than its To list or
+/// when one side has neither `send_each_at` nor `send_at`.
+fn combine_recipients(header: &mut Header, other: &Header) -> Result<(), SmtpApiError> {
+    let ours = header.to.len();
+    let theirs = other.to.len();
+
+    check_sub(header)?;
+    check_sub(other)?;
+
+    let our_timestamps = aligned_send_each_at(header)?;
+    let their_timestamps = aligned_send_each_at(other)?;
+    let send_each_at = if our_timestamps.is_none() && their_timestamps.is_none() {
+        None
+    } else {
+        let left = side_timestamps(our_timestamps, header.send_at, ours);
+        let right = side_timestamps(their_timestamps, other.send_at, theirs);
+
+        match (left, right) {
+            (Some(mut left), Some(right)) => {
+                left.extend(right);
+                Some(left)
+            },
+            (left, right) => {
+                let found = left.or(right).map(|timestamps| timestamps.len()).unwrap_or(0);
+                return Err(SmtpApiError::MisalignedSendEachAt { expected: ours + theirs, found });
+            },
+        }
+    };
+
+    let default = header.sub_default.clone();
+
+    for (tag, values) in header.sub.iter_mut() {
+        values.resize(ours, default.clone());
+        if let Some(other_values) = other.sub.get(tag) {
+            values.extend(other_values.iter().cloned());
+        }
+        values.resize(ours + theirs, default.clone());
+    }
+
+    for (tag, other_values) in &other.sub {
+        if !header.sub.contains_key(tag) {
+            let mut values = vec![default.clone(); ours];
+            values.extend(other_values.iter().cloned());
+            values.resize(ours + theirs, default.clone());
+            header.sub.insert(tag.clone(), values);
+        }
+    }
+
+    header.to.extend_from_slice(&other.to);
+
+    if send_each_at.is_some() {
+        header.send_each_at = send_each_at;
+        header.send_at = None;
+    }
+
+    Ok(())
+}
+
+pub fn merge(header: &mut Header, other: &Header, strategy: MergeStrategy) -> Result<(), SmtpApiError> {
+    // The `send_at` of appended recipients is already in `send_each_at`.
+    let mut scheduled = false;
+
+    match strategy.recipients {
+        MergePolicy::Keep => {},
+        MergePolicy::Replace => if !other.to.is_empty() {
+            header.to = other.to.clone();
+            header.sub = other.sub.clone();
+            header.send_each_at = other.send_each_at.clone();
+            if header.send_each_at.is_some() {
+                header.send_at = None;
+            }
+        },
+        MergePolicy::Combine => {
+            combine_recipients(header, other)?;
+            scheduled = !other.to.is_empty() && header.send_each_at.is_some();
+        },
+    }
+
+    merge_map(&mut header.section, &other.section, strategy.sections);
+    merge_list(&mut header.categories, &other.categories, strategy.categories);
+    merge_map(&mut header.unique_args, &other.unique_args, strategy.unique_args);
+
+    match strategy.filters {
+        MergePolicy::Combine => {
+            for (name, filter) in &other.filters {
+                let settings = &mut header.filters.entry(name.clone()).or_default().settings;
+                settings.extend(filter.settings.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+        },
+        policy => merge_map(&mut header.filters, &other.filters, policy),
+    }
+
+    merge_scalar(&mut header.asm_group_id, &other.asm_group_id, strategy.scalars);
+    merge_scalar(&mut header.ip_pool, &other.ip_pool, strategy.scalars);
+    merge_scalar(&mut header.batch_id, &other.batch_id, strategy.scalars);
+    if !scheduled && merge_scalar(&mut header.send_at, &other.send_at, strategy.scalars) {
+        header.send_each_at = None;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use super::{AddressError, Header, Mailbox};
+
+/// A read-only view of one recipient of a `Header`: its address and the
+/// values found at its position in the substitution columns and in
+/// `send_each_at`.
+#[derive(Debug, Clone, Copy)]
+pub struct Recipient<'a> {
+    header: &'a Header,
+    index: usize
+}
+
+impl<'a> Recipient<'a> {
+    pub(crate) fn new(header: &'a Header, index: usize) -> Recipient<'a> {
+        Recipient { header, index }
+    }
+
+    /// Returns the position of the recipient in the To list.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the address of the recipient.
+    pub fn email(&self) -> &'a str {
+        &self.header.to[self.index]
+    }
+
+    /// Returns the address of the recipient with its display name decoded.
+    pub fn mailbox(&self) -> Result<Mailbox, AddressError> {
+        self.email().parse()
+    }
+
+    /// Returns the value of the substitution `tag` for this recipient, if the
+    /// column has one at this position.
+    pub fn substitution(&self, tag: &str) -> Option<&'a str> {
+        self.header.sub.get(tag)
+                       .and_then(|values| values.get(self.index))
+                       .map(|value| value.as_str())
+    }
+
+    /// Returns the substitutions of this recipient, sorted by tag.
+    pub fn substitutions(&self) -> Vec<(&'a str, &'a str)> {
+        let index = self.index;
+        let mut subs: Vec<(&'a str, &'a str)> = self.header.sub.iter()
+            .filter_map(|(tag, values)| values.get(index).map(|value| (tag.as_str(), value.as_str())))
+            .collect();
+        subs.sort();
+        subs
+    }
+
+    /// Returns when the message to this recipient is scheduled, from
+    /// `send_each_at` or else `send_at`.
+    pub fn send_at(&self) -> Option<i64> {
+        match self.header.send_each_at {
+            Some(ref timestamps) => timestamps.get(self.index).cloned(),
+            None => self.header.send_at,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use super::{Header, SmtpApiError};
+
+/// When SendGrid delivers the message, as set by `send_at` and `send_each_at`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Schedule {
+    /// The message is sent as soon as it is received.
+    #[default]
+    Immediate,
+    /// Every recipient gets the message at this UNIX timestamp (`send_at`).
+    At(i64),
+    /// Each recipient gets the message at the timestamp found at its
+    /// position in the To list (`send_each_at`).
+    EachAt(Vec<i64>),
+}
+
+pub fn get(header: &Header) -> Schedule {
+    match (header.send_at, header.send_each_at.as_ref()) {
+        (_, Some(timestamps)) => Schedule::EachAt(timestamps.clone()),
+        (Some(timestamp), None) => Schedule::At(timestamp),
+        (None, None) => Schedule::Immediate,
+    }
+}
+
+pub fn set(header: &mut Header, schedule: Schedule) {
+    match schedule {
+        Schedule::Immediate => {
+            header.send_at = None;
+            header.send_each_at = None;
+        },
+        Schedule::At(timestamp) => {
+            header.send_at = Some(timestamp);
+            header.send_each_at = None;
+        },
+        Schedule::EachAt(timestamps) => {
+            header.send_at = None;
+            header.send_each_at = Some(timestamps);
+        },
+    }
+}
+
+pub fn try_set(header: &mut Header, schedule: Schedule) -> Result<(), SmtpApiError> {
+    let conflict = header.send_at.is_some() && header.send_each_at.is_some();
+    let current = get(header);
+
+    if conflict || (current != Schedule::Immediate && current != schedule) {
+        return Err(SmtpApiError::ScheduleConflict);
+    }
+
+    set(header, schedule);
+    Ok(())
+}
@@ -0,0 +1,287 @@
+//! `Serialize`/`Deserialize` for `Header` and `Filter`, following the
+//! X-SMTPAPI wire format: the same keys, the same omission of empty fields
+//! and the same sorted order as `to_json_string()`.
+//!
+//! Both are written as maps of known length and read back with
+//! `deserialize_map`, so non-self-describing formats such as bincode work
+//! too. The loose values accepted from JSON (numeric filter settings, a bare
+//! category string, `null` for unset fields) are only accepted from
+//! human-readable formats.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use serde::Deserialize;
+use serde::de::{self, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+use super::{Filter, Header};
+
+fn sorted<V>(map: &HashMap<String, V>) -> BTreeMap<&String, &V> {
+    map.iter().collect()
+}
+
+impl Serialize for Filter {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let len = if self.settings.is_empty() { 0 } else { 1 };
+        let mut map = serializer.serialize_map(Some(len))?;
+        if !self.settings.is_empty() {
+            map.serialize_entry("settings", &sorted(&self.settings))?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Header {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let len = [self.asm_group_id.is_some(),
+                   self.batch_id.is_some(),
+                   !self.categories.is_empty(),
+                   !self.filters.is_empty(),
+                   self.ip_pool.is_some(),
+                   !self.section.is_empty(),
+                   self.send_at.is_some(),
+                   self.send_each_at.is_some(),
+                   !self.sub.is_empty(),
+                   !self.to.is_empty(),
+                   !self.unique_args.is_empty()].iter().filter(|&&set| set).count();
+        let mut map = serializer.serialize_map(Some(len))?;
+
+        if let Some(ref x) = self.asm_group_id {
+            map.serialize_entry("asm_group_id", x)?;
+        }
+        if let Some(ref x) = self.batch_id {
+            map.serialize_entry("batch_id", x)?;
+        }
+        if !self.categories.is_empty() {
+            map.serialize_entry("category", &self.categories)?;
+        }
+        if !self.filters.is_empty() {
+            map.serialize_entry("filters", &sorted(&self.filters))?;
+        }
+        if let Some(ref x) = self.ip_pool {
+            map.serialize_entry("ip_pool", x)?;
+        }
+        if !self.section.is_empty() {
+            map.serialize_entry("section", &sorted(&self.section))?;
+        }
+        if let Some(ref x) = self.send_at {
+            map.serialize_entry("send_at", x)?;
+        }
+        if let Some(ref x) = self.send_each_at {
+            map.serialize_entry("send_each_at", x)?;
+        }
+        if !self.sub.is_empty() {
+            map.serialize_entry("sub", &sorted(&self.sub))?;
+        }
+        if !self.to.is_empty() {
+            map.serialize_entry("to", &self.to)?;
+        }
+        if !self.unique_args.is_empty() {
+            map.serialize_entry("unique_args", &sorted(&self.unique_args))?;
+        }
+
+        map.end()
+    }
+}
+
+/// A filter setting; other producers commonly send `1` or `true` instead of
+/// a string, so scalars are accepted like `Header::from_json_str` does.
+struct Setting(String);
+
+impl<'de> Deserialize<'de> for Setting {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Setting, D::Error> {
+        struct SettingVisitor;
+
+        impl<'de> Visitor<'de> for SettingVisitor {
+            type Value = Setting;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string, number or boolean")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Setting, E> {
+                Ok(Setting(v.to_owned()))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Setting, E> {
+                Ok(Setting(v.to_string()))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Setting, E> {
+                Ok(Setting(v.to_string()))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Setting, E> {
+                Ok(Setting(v.to_string()))
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> Result<Setting, E> {
+                Ok(Setting(v.to_string()))
+            }
+        }
+
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_any(SettingVisitor)
+        } else {
+            deserializer.deserialize_string(SettingVisitor)
+        }
+    }
+}
+
+/// SendGrid accepts a single category as a bare string.
+fn categories<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    struct CategoriesVisitor;
+
+    impl<'de> Visitor<'de> for CategoriesVisitor {
+        type Value = Vec<String>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a string or an array of strings")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Vec<String>, E> {
+            Ok(vec![v.to_owned()])
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<String>, A::Error> {
+            let mut categories = Vec::new();
+            while let Some(category) = seq.next_element()? {
+                categories.push(category);
+            }
+            Ok(categories)
+        }
+    }
+
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(CategoriesVisitor)
+    } else {
+        deserializer.deserialize_seq(CategoriesVisitor)
+    }
+}
+
+/// Wraps `categories` so that it can be read as a map value.
+struct Categories(Vec<String>);
+
+impl<'de> Deserialize<'de> for Categories {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Categories, D::Error> {
+        categories(deserializer).map(Categories)
+    }
+}
+
+const FILTER_FIELDS: &[&str] = &["settings"];
+
+const HEADER_FIELDS: &[&str] = &["asm_group_id", "batch_id", "category", "filters", "ip_pool", "section",
+                                 "send_at", "send_each_at", "sub", "to", "unique_args"];
+
+/// Reads the value of an optional field. Human-readable formats may write
+/// `null` for an unset field; the others only hold the fields that are set.
+fn optional<'de, A, T>(map: &mut A, human_readable: bool) -> Result<Option<T>, A::Error>
+    where A: MapAccess<'de>, T: Deserialize<'de> {
+    if human_readable { map.next_value() } else { map.next_value().map(Some) }
+}
+
+/// Stores `value` in `slot`, refusing a field seen twice.
+fn once<T, E: de::Error>(slot: &mut Option<T>, field: &'static str, value: T) -> Result<(), E> {
+    if slot.is_some() {
+        return Err(E::duplicate_field(field));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+struct FilterVisitor;
+
+impl<'de> Visitor<'de> for FilterVisitor {
+    type Value = Filter;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an X-SMTPAPI filter")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Filter, A::Error> {
+        let mut settings: Option<HashMap<String, Setting>> = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "settings" => once(&mut settings, "settings", map.next_value()?)?,
+                _ => return Err(de::Error::unknown_field(&key, FILTER_FIELDS)),
+            }
+        }
+
+        let mut filter = Filter::new();
+        filter.settings = settings.unwrap_or_default().into_iter().map(|(k, v)| (k, v.0)).collect();
+        Ok(filter)
+    }
+}
+
+impl<'de> Deserialize<'de> for Filter {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Filter, D::Error> {
+        deserializer.deserialize_map(FilterVisitor)
+    }
+}
+
+struct HeaderVisitor {
+    human_readable: bool,
+}
+
+impl<'de> Visitor<'de> for HeaderVisitor {
+    type Value = Header;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an X-SMTPAPI header")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Header, A::Error> {
+        let mut to = None;
+        let mut sub = None;
+        let mut section = None;
+        let mut categories: Option<Categories> = None;
+        let mut unique_args = None;
+        let mut filters = None;
+        let mut asm_group_id = None;
+        let mut send_at = None;
+        let mut send_each_at = None;
+        let mut ip_pool = None;
+        let mut batch_id = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "to" => once(&mut to, "to", map.next_value()?)?,
+                "sub" => once(&mut sub, "sub", map.next_value()?)?,
+                "section" => once(&mut section, "section", map.next_value()?)?,
+                "category" => once(&mut categories, "category", map.next_value()?)?,
+                "unique_args" => once(&mut unique_args, "unique_args", map.next_value()?)?,
+                "filters" => once(&mut filters, "filters", map.next_value()?)?,
+                "asm_group_id" => once(&mut asm_group_id, "asm_group_id", optional(&mut map, self.human_readable)?)?,
+                "send_at" => once(&mut send_at, "send_at", optional(&mut map, self.human_readable)?)?,
+                "send_each_at" => once(&mut send_each_at, "send_each_at", optional(&mut map, self.human_readable)?)?,
+                "ip_pool" => once(&mut ip_pool, "ip_pool", optional(&mut map, self.human_readable)?)?,
+                "batch_id" => once(&mut batch_id, "batch_id", optional(&mut map, self.human_readable)?)?,
+                _ => return Err(de::Error::unknown_field(&key, HEADER_FIELDS)),
+            }
+        }
+
+        let mut header = Header::new();
+
+        header.to = to.unwrap_or_default();
+        header.sub = sub.unwrap_or_default();
+        header.section = section.unwrap_or_default();
+        header.categories = categories.map(|c| c.0).unwrap_or_default();
+        header.unique_args = unique_args.unwrap_or_default();
+        header.filters = filters.unwrap_or_default();
+        header.asm_group_id = asm_group_id.flatten();
+        header.send_at = send_at.flatten();
+        header.send_each_at = send_each_at.flatten();
+        header.ip_pool = ip_pool.flatten();
+        header.batch_id = batch_id.flatten();
+
+        Ok(header)
+    }
+}
+
+impl<'de> Deserialize<'de> for Header {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Header, D::Error> {
+        let human_readable = deserializer.is_human_readable();
+        deserializer.deserialize_map(HeaderVisitor { human_readable })
+    }
+}
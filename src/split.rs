@@ -0,0 +1,126 @@
+use std::cmp;
+use std::error;
+use std::fmt;
+use rustc_serialize::json::ToJson;
+
+use super::{encoding, Header};
+
+/// SendGrid recommends no more than this many recipients per X-SMTPAPI header.
+pub const MAX_RECIPIENTS: usize = 1000;
+
+fn range<T: Clone>(values: &[T], start: usize, end: usize) -> Vec<T> {
+    let end = cmp::min(end, values.len());
+    if start < end { values[start..end].to_vec() } else { Vec::new() }
+}
+
+/// Returns a copy of `header` limited to the recipients in `start..end`: the
+/// matching positions of `to`, of every substitution column and of
+/// `send_each_at` are kept and every other field is cloned.
+pub fn slice(header: &Header, start: usize, end: usize) -> Header {
+    Header {
+        to: range(&header.to, start, end),
+        sub: header.sub.iter().map(|(tag, values)| (tag.clone(), range(values, start, end))).collect(),
+        section: header.section.clone(),
+        categories: header.categories.clone(),
+        unique_args: header.unique_args.clone(),
+        filters: header.filters.clone(),
+        asm_group_id: header.asm_group_id,
+        send_at: header.send_at,
+        send_each_at: header.send_each_at.as_ref().map(|timestamps| range(timestamps, start, end)),
+        ip_pool: header.ip_pool.clone(),
+        batch_id: header.batch_id.clone(),
+        encoding: header.encoding,
+        sub_default: header.sub_default.clone()
+    }
+}
+
+/// A recipient whose header is larger than the byte budget even when it is
+/// the only recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OversizedRecipient {
+    /// Position of the recipient in the To list.
+    pub index: usize,
+    /// Address of the recipient.
+    pub email: String,
+    /// Size in bytes of `to_json_string()` for a header holding only this recipient.
+    pub len: usize,
+}
+
+impl fmt::Display for OversizedRecipient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "to[{}] `{}` needs a {} bytes header on its own", self.index, self.email, self.len)
+    }
+}
+
+impl error::Error for OversizedRecipient {}
+
+fn json_len<T: ToJson>(header: &Header, value: &T) -> usize {
+    encoding::encode(value.to_json().to_string(), header.encoding).len()
+}
+
+/// Bytes added to the serialized header by the recipient at `index` when it
+/// follows another recipient: its entry in `to`, in every substitution
+/// column and in `send_each_at`, each preceded by a comma.
+fn recipient_len(header: &Header, index: usize) -> usize {
+    let mut len = json_len(header, &header.to[index]) + 1;
+
+    for values in header.sub.values() {
+        if let Some(value) = values.get(index) {
+            len += json_len(header, value) + 1;
+        }
+    }
+    if let Some(timestamp) = header.send_each_at.as_ref().and_then(|timestamps| timestamps.get(index)) {
+        len += timestamp.to_string().len() + 1;
+    }
+
+    len
+}
+
+pub fn by_size(header: &Header, max_bytes: usize) -> Result<Vec<Header>, Vec<OversizedRecipient>> {
+    let count = header.to.len();
+
+    if count == 0 {
+        return Ok(vec![header.clone()]);
+    }
+
+    let mut parts = Vec::new();
+    let mut oversized = Vec::new();
+    let mut start = 0;
+
+    while start < count {
+        let len = slice(header, start, start + 1).to_json_string().len();
+        if len > max_bytes {
+            oversized.push(OversizedRecipient { index: start, email: header.to[start].clone(), len });
+            start += 1;
+            continue;
+        }
+
+        let mut end = start + 1;
+        let mut total = len;
+        while end < count {
+            let next = recipient_len(header, end);
+            if total + next > max_bytes {
+                break;
+            }
+            total += next;
+            end += 1;
+        }
+
+        parts.push(slice(header, start, end));
+        start = end;
+    }
+
+    if oversized.is_empty() { Ok(parts) } else { Err(oversized) }
+}
+
+pub fn by_count(header: &Header, max_recipients: usize) -> Vec<Header> {
+    assert!(max_recipients > 0, "max_recipients must be greater than zero");
+
+    if header.to.is_empty() {
+        return vec![header.clone()];
+    }
+
+    (0..header.to.len()).step_by(max_recipients)
+                        .map(|start| slice(header, start, start + max_recipients))
+                        .collect()
+}
@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Rate {
+    PerHour(u32),
+    Over(Duration),
+}
+
+/// How `Header::spread_send_each_at` spaces the recipients of the To list.
+///
+/// Each recipient gets a slot of the same length, starting from the given
+/// time. Without jitter a recipient is sent at the start of its slot; with
+/// jitter, at a pseudo-random time within it, derived from a seed so that
+/// the same seed always gives the same schedule.
+///
+/// # Examples
+///
+/// ```
+/// use smtpapi::Spread;
+/// use std::time::Duration;
+///
+/// let hourly = Spread::per_hour(500);
+/// let mut overnight = Spread::over(Duration::from_secs(8 * 3600));
+/// overnight.jitter(42);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spread {
+    rate: Rate,
+    seed: Option<u64>
+}
+
+impl Spread {
+    /// It sends at most `messages` messages per hour.
+    ///
+    /// A rate of zero is refused by `Header::spread_send_each_at`.
+    pub fn per_hour(messages: u32) -> Spread {
+        Spread { rate: Rate::PerHour(messages), seed: None }
+    }
+
+    /// It sends every message within `window`.
+    pub fn over(window: Duration) -> Spread {
+        Spread { rate: Rate::Over(window), seed: None }
+    }
+
+    /// It sends each message at a pseudo-random time within its slot.
+    pub fn jitter(&mut self, seed: u64) -> &mut Spread {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Returns true if no message can ever be sent at this rate.
+    pub(crate) fn is_zero_rate(&self) -> bool {
+        self.rate == Rate::PerHour(0)
+    }
+
+    /// Returns the length of the slot of each recipient, in seconds.
+    fn interval(&self, count: usize) -> f64 {
+        match self.rate {
+            Rate::PerHour(messages) => 3600.0 / f64::from(messages),
+            Rate::Over(window) => if count == 0 { 0.0 } else { window.as_secs_f64() / count as f64 },
+        }
+    }
+
+    /// Returns the timestamps of `count` recipients starting at `start`.
+    pub(crate) fn timestamps(&self, start: i64, count: usize) -> Vec<i64> {
+        let interval = self.interval(count);
+        let mut state = self.seed.unwrap_or(0);
+
+        (0..count).map(|i| {
+            let offset = match self.seed {
+                Some(_) => unit(splitmix64(&mut state)),
+                None => 0.0,
+            };
+            start + ((i as f64 + offset) * interval) as i64
+        }).collect()
+    }
+}
+
+/// SplitMix64, a small generator whose output only depends on the seed.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Maps `x` to a float in `[0, 1)`.
+fn unit(x: u64) -> f64 {
+    (x >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn even_spacing() {
+        assert_eq!(vec![100, 112, 124, 136], Spread::per_hour(300).timestamps(100, 4));
+        assert_eq!(vec![100, 125, 150, 175], Spread::over(Duration::from_secs(100)).timestamps(100, 4));
+        assert!(Spread::over(Duration::from_secs(100)).timestamps(100, 0).is_empty());
+        assert_eq!(vec![100, 100], Spread::over(Duration::from_secs(0)).timestamps(100, 2));
+    }
+
+    #[test]
+    fn zero_rate() {
+        assert!(Spread::per_hour(0).is_zero_rate());
+        assert!(!Spread::per_hour(1).is_zero_rate());
+        assert!(!Spread::over(Duration::from_secs(0)).is_zero_rate());
+    }
+
+    #[test]
+    fn jitter_stays_in_slot() {
+        let mut spread = Spread::per_hour(60);
+        spread.jitter(7);
+
+        let timestamps = spread.timestamps(0, 100);
+        for (i, &timestamp) in timestamps.iter().enumerate() {
+            assert!(timestamp >= i as i64 * 60 && timestamp < (i as i64 + 1) * 60);
+        }
+        assert_eq!(timestamps, spread.timestamps(0, 100));
+        assert!(timestamps != Spread::per_hour(60).timestamps(0, 100));
+    }
+}
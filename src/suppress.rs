@@ -0,0 +1,307 @@
+use std::collections::HashSet;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use super::{AddressError, EmailAddress};
+
+/// A local suppression list, such as unsubscribes or bounces, applied with
+/// `Header::apply_suppressions`.
+///
+/// An entry is either an exact address or a domain wildcard written
+/// `*@domain.com`, which matches every address of that domain. Addresses
+/// and domains are compared case-insensitively, and display names are
+/// ignored.
+///
+/// # Examples
+///
+/// ```
+/// use smtpapi::{Suppressions};
+///
+/// let mut suppressions = Suppressions::new();
+/// suppressions.try_add("bounced@domain.com").unwrap()
+///             .try_add("*@example.org").unwrap();
+///
+/// assert!(suppressions.contains("John <Bounced@Domain.com>"));
+/// assert!(suppressions.contains("anyone@example.org"));
+/// assert!(!suppressions.contains("anyone@sub.example.org"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Suppressions {
+    addresses: HashSet<String>,
+    domains: HashSet<String>
+}
+
+/// A line of a suppression list that is not an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidEntry {
+    /// Line number, starting at 1.
+    pub line: usize,
+    /// The first column of the line, unquoted.
+    pub entry: String,
+    /// Why the entry is not an address nor a `*@domain.com` wildcard.
+    pub reason: AddressError,
+}
+
+impl fmt::Display for InvalidEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {} `{}` is not a suppression entry: {}", self.line, self.entry, self.reason)
+    }
+}
+
+impl error::Error for InvalidEntry {}
+
+/// Error returned when a suppression list cannot be loaded.
+#[derive(Debug)]
+pub enum SuppressionsError {
+    /// The list could not be read.
+    Io(io::Error),
+    /// Some lines are not entries; every one of them is reported.
+    InvalidEntries(Vec<InvalidEntry>),
+}
+
+impl fmt::Display for SuppressionsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SuppressionsError::Io(ref e) => e.fmt(f),
+            SuppressionsError::InvalidEntries(ref entries) => {
+                write!(f, "{} lines are not suppression entries", entries.len())?;
+                for entry in entries {
+                    write!(f, "\n{}", entry)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+impl error::Error for SuppressionsError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            SuppressionsError::Io(ref e) => Some(e),
+            SuppressionsError::InvalidEntries(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for SuppressionsError {
+    fn from(e: io::Error) -> SuppressionsError {
+        SuppressionsError::Io(e)
+    }
+}
+
+/// Returns the lowercased addr-spec of `email`.
+fn normalize(email: &str) -> Result<String, AddressError> {
+    email.parse::<EmailAddress>().map(|address| address.to_string().to_lowercase())
+}
+
+/// Returns the first CSV field of `line`, unquoted.
+///
+/// The field ends at the first comma found outside of double quotes. It is
+/// unquoted when it is a CSV quoted field, with `""` standing for a quote,
+/// and kept as written otherwise, so that a quoted local part such as
+/// `"john doe"@domain.com` may be written without CSV quoting.
+fn first_field(line: &str) -> String {
+    let mut in_quotes = false;
+    let end = line.char_indices()
+                  .find(|&(_, c)| {
+                      if c == '"' {
+                          in_quotes = !in_quotes;
+                      }
+                      c == ',' && !in_quotes
+                  })
+                  .map_or(line.len(), |(i, _)| i);
+    let field = line[..end].trim();
+
+    if field.len() >= 2 && field.starts_with('"') && field.ends_with('"') {
+        let inner = &field[1..field.len() - 1];
+        if inner.replace("\"\"", "").find('"').is_none() {
+            return inner.replace("\"\"", "\"");
+        }
+    }
+
+    field.to_owned()
+}
+
+impl Suppressions {
+    /// Constructs an empty `Suppressions` list.
+    pub fn new() -> Suppressions {
+        Suppressions::default()
+    }
+
+    /// It adds an exact address or a `*@domain.com` wildcard, if `entry` is one
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{AddressError, Suppressions};
+    ///
+    /// let mut suppressions = Suppressions::new();
+    /// suppressions.try_add("*@example.org").unwrap();
+    ///
+    /// assert_eq!(Err(AddressError::InvalidDomain), suppressions.try_add("bounced@domain,com").map(|_| ()));
+    /// ```
+    pub fn try_add<S>(&mut self, entry: S) -> Result<&mut Suppressions, AddressError> where S: AsRef<str> {
+        let entry = entry.as_ref().trim();
+
+        if let Some(domain) = entry.strip_prefix("*@") {
+            let address: EmailAddress = format!("wildcard@{}", domain).parse()?;
+            self.domains.insert(address.domain().to_lowercase());
+        } else {
+            self.addresses.insert(normalize(entry)?);
+        }
+
+        Ok(self)
+    }
+
+    /// Reads a list with one entry per line, or a CSV file whose first
+    /// column holds the entries.
+    ///
+    /// A leading UTF-8 byte order mark, blank lines and lines starting with
+    /// `#` are skipped, as is a first row that looks like a header: it is not
+    /// an entry and either has no `@` or has several columns. The first column
+    /// may be double-quoted. Every other line that is not an entry is
+    /// reported, and no list is returned then.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use smtpapi::{Suppressions, SuppressionsError};
+    ///
+    /// let csv = "\u{feff}email,reason\n\"bounced@domain.com\",bounce\n*@example.org,unsubscribe\n";
+    /// let suppressions = Suppressions::from_reader(csv.as_bytes()).unwrap();
+    ///
+    /// assert_eq!(2, suppressions.len());
+    ///
+    /// match Suppressions::from_reader("email\nbounced@domain..com\n".as_bytes()) {
+    ///     Err(SuppressionsError::InvalidEntries(entries)) => assert_eq!(2, entries[0].line),
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Suppressions, SuppressionsError> {
+        let mut suppressions = Suppressions::new();
+        let mut invalid = Vec::new();
+        let mut first_row = true;
+
+        for (index, line) in reader.lines().enumerate() {
+            let line = line?;
+            let line = if index == 0 { line.trim_start_matches('\u{feff}') } else { &line };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let entry = first_field(line);
+            if let Err(reason) = suppressions.try_add(&entry) {
+                let header = first_row && (!entry.contains('@') || line.contains(','));
+                if !header {
+                    invalid.push(InvalidEntry { line: index + 1, entry, reason });
+                }
+            }
+            first_row = false;
+        }
+
+        if !invalid.is_empty() {
+            return Err(SuppressionsError::InvalidEntries(invalid));
+        }
+
+        Ok(suppressions)
+    }
+
+    /// Reads a newline-separated or CSV file, as `from_reader` does.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Suppressions, SuppressionsError> {
+        Suppressions::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Returns true if `email`, bare or in the `Name <address>` form, is suppressed.
+    pub fn contains(&self, email: &str) -> bool {
+        match normalize(email) {
+            Ok(address) => {
+                self.addresses.contains(&address) ||
+                    address.rfind('@').is_some_and(|at| self.domains.contains(&address[at + 1..]))
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the number of entries.
+    pub fn len(&self) -> usize {
+        self.addresses.len() + self.domains.len()
+    }
+
+    /// Returns true if there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty() && self.domains.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_exact_or_wildcards() {
+        let mut suppressions = Suppressions::new();
+        suppressions.try_add(" Foo@Domain.com ").unwrap()
+                    .try_add("*@Example.ORG").unwrap();
+        assert_eq!(Err(AddressError::MissingAt), suppressions.try_add("email").map(|_| ()));
+        assert_eq!(Err(AddressError::InvalidDomain), suppressions.try_add("*@").map(|_| ()));
+
+        assert_eq!(2, suppressions.len());
+        assert!(suppressions.contains("foo@domain.com"));
+        assert!(suppressions.contains("\"Doe, John\" <john@example.org>"));
+        assert!(!suppressions.contains("bar@domain.com"));
+        assert!(!suppressions.contains("not an address"));
+    }
+
+    #[test]
+    fn lists_are_read_line_by_line() {
+        let list = "# bounces\nfoo@domain.com\n\n  bar@domain.com  \r\n\"*@example.org\",\"unsubscribe\"\n";
+        let suppressions = Suppressions::from_reader(list.as_bytes()).unwrap();
+
+        assert_eq!(3, suppressions.len());
+        assert!(suppressions.contains("bar@domain.com"));
+        assert!(suppressions.contains("anyone@example.org"));
+    }
+
+    #[test]
+    fn csv_fields_are_unquoted() {
+        assert_eq!("foo@domain.com", first_field("foo@domain.com,bounce"));
+        assert_eq!("foo@domain.com", first_field(" \"foo@domain.com\" ,\"a, b\""));
+        assert_eq!("\"john doe\"@domain.com", first_field("\"john doe\"@domain.com,bounce"));
+        assert_eq!("\"john, doe\"@domain.com", first_field("\"\"\"john, doe\"\"@domain.com\",bounce"));
+        assert_eq!("\"Doe, John\" <john@domain.com>", first_field("\"Doe, John\" <john@domain.com>,bounce"));
+    }
+
+    #[test]
+    fn lists_with_bom_and_bad_lines() {
+        let list = "\u{feff}unsub@domain.com\n\"john doe\"@domain.com\n";
+        let suppressions = Suppressions::from_reader(list.as_bytes()).unwrap();
+
+        assert_eq!(2, suppressions.len());
+        assert!(suppressions.contains("unsub@domain.com"));
+        assert!(suppressions.contains("\"john doe\"@domain.com"));
+
+        let list = "email,reason\nfoo@domain.com\nfoo@domain..com\n*@\n";
+        match Suppressions::from_reader(list.as_bytes()) {
+            Err(SuppressionsError::InvalidEntries(entries)) => assert_eq!(vec![
+                InvalidEntry { line: 3, entry: "foo@domain..com".to_string(), reason: AddressError::InvalidDomain },
+                InvalidEntry { line: 4, entry: "*@".to_string(), reason: AddressError::InvalidDomain },
+            ], entries),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let list = "bounced@domain..com\nfoo@domain.com\n";
+        match Suppressions::from_reader(list.as_bytes()) {
+            Err(SuppressionsError::InvalidEntries(entries)) => assert_eq!(vec![
+                InvalidEntry { line: 1, entry: "bounced@domain..com".to_string(), reason: AddressError::InvalidDomain },
+            ], entries),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        let list = "\"Email Address\",\"Reason\"\nfoo@domain.com\n";
+        assert_eq!(1, Suppressions::from_reader(list.as_bytes()).unwrap().len());
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::SmtpApiError;
+
+/// SendGrid schedules messages at most this many seconds ahead.
+pub const MAX_SCHEDULE_SECS: i64 = 72 * 60 * 60;
+
+/// A point in time that can be used for `send_at` and `send_each_at`, which
+/// SendGrid expects as UNIX timestamps in seconds.
+///
+/// It is implemented for `SystemTime`, for `chrono::DateTime<Utc>` with the
+/// `chrono` feature and for `time::OffsetDateTime` with the `time` feature.
+pub trait SendTime: Sized {
+    /// Returns the UNIX timestamp, in seconds, rounded down.
+    fn to_timestamp(&self) -> i64;
+
+    /// Returns the point in time of a UNIX timestamp, if it can be represented.
+    fn from_timestamp(timestamp: i64) -> Option<Self>;
+}
+
+impl SendTime for SystemTime {
+    fn to_timestamp(&self) -> i64 {
+        match self.duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_secs() as i64,
+            Err(e) => {
+                let before = e.duration();
+                -(before.as_secs() as i64) - if before.subsec_nanos() > 0 { 1 } else { 0 }
+            },
+        }
+    }
+
+    fn from_timestamp(timestamp: i64) -> Option<SystemTime> {
+        if timestamp >= 0 {
+            UNIX_EPOCH.checked_add(Duration::from_secs(timestamp as u64))
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::from_secs(timestamp.unsigned_abs()))
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl SendTime for ::chrono::DateTime<::chrono::Utc> {
+    fn to_timestamp(&self) -> i64 {
+        self.timestamp()
+    }
+
+    fn from_timestamp(timestamp: i64) -> Option<::chrono::DateTime<::chrono::Utc>> {
+        ::chrono::DateTime::from_timestamp(timestamp, 0)
+    }
+}
+
+#[cfg(feature = "time")]
+impl SendTime for ::time::OffsetDateTime {
+    fn to_timestamp(&self) -> i64 {
+        self.unix_timestamp()
+    }
+
+    fn from_timestamp(timestamp: i64) -> Option<::time::OffsetDateTime> {
+        ::time::OffsetDateTime::from_unix_timestamp(timestamp).ok()
+    }
+}
+
+/// Returns the current UNIX timestamp.
+pub fn now() -> i64 {
+    SystemTime::now().to_timestamp()
+}
+
+/// Checks that `timestamp` is neither before `now` nor more than
+/// `MAX_SCHEDULE_SECS` after it.
+pub fn check(timestamp: i64, now: i64) -> Result<i64, SmtpApiError> {
+    let latest = now + MAX_SCHEDULE_SECS;
+
+    if timestamp < now || timestamp > latest {
+        return Err(SmtpApiError::SendTimeOutOfWindow { timestamp, earliest: now, latest });
+    }
+
+    Ok(timestamp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_time_round_trip() {
+        for &timestamp in &[0, 1453213937, -1, -1453213937] {
+            let time = SystemTime::from_timestamp(timestamp).unwrap();
+            assert_eq!(timestamp, time.to_timestamp());
+        }
+
+        let time = UNIX_EPOCH - Duration::from_millis(1500);
+        assert_eq!(-2, time.to_timestamp());
+    }
+
+    #[test]
+    fn schedule_window() {
+        let now = 1453213937;
+
+        assert_eq!(Ok(now), check(now, now));
+        assert_eq!(Ok(now + MAX_SCHEDULE_SECS), check(now + MAX_SCHEDULE_SECS, now));
+        assert!(check(now - 1, now).is_err());
+        assert_eq!(Err(SmtpApiError::SendTimeOutOfWindow { timestamp: now + MAX_SCHEDULE_SECS + 1, earliest: now, latest: now + MAX_SCHEDULE_SECS }),
+                   check(now + MAX_SCHEDULE_SECS + 1, now));
+    }
+}
@@ -0,0 +1,70 @@
+use chrono::{Duration, LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+
+use super::SmtpApiError;
+
+/// Returns the UNIX timestamp of the wall-clock time `local` in `tz`.
+///
+/// A time that happens twice, when clocks are turned back, resolves to the
+/// first occurrence. A time that is skipped, when clocks are turned forward,
+/// is read with the offset in effect before the change, which moves it
+/// forward by the length of the gap (02:30 becomes 03:30).
+pub fn resolve(tz: &Tz, local: &NaiveDateTime) -> i64 {
+    match tz.from_local_datetime(local) {
+        LocalResult::Single(time) | LocalResult::Ambiguous(time, _) => time.timestamp(),
+        LocalResult::None => {
+            let before = tz.offset_from_utc_datetime(&(*local - Duration::days(1))).fix();
+            (*local - Duration::seconds(i64::from(before.local_minus_utc()))).and_utc().timestamp()
+        },
+    }
+}
+
+/// Returns the UNIX timestamps of `local` in each of the `timezones`, given
+/// as IANA names such as `Europe/Paris`.
+pub fn timestamps<S: AsRef<str>>(local: &NaiveDateTime, timezones: &[S]) -> Result<Vec<i64>, SmtpApiError> {
+    timezones.iter()
+             .enumerate()
+             .map(|(index, name)| {
+                 let name = name.as_ref();
+                 name.parse::<Tz>()
+                     .map(|tz| resolve(&tz, local))
+                     .map_err(|_| SmtpApiError::UnknownTimezone { index, name: name.to_owned() })
+             })
+             .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn local(month: u32, day: u32, hour: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap().and_hms_opt(hour, min, 0).unwrap()
+    }
+
+    #[test]
+    fn resolves_regular_times() {
+        assert_eq!(1705305600, resolve(&Tz::Europe__Paris, &local(1, 15, 9, 0)));
+        assert_eq!(1705327200, resolve(&Tz::America__New_York, &local(1, 15, 9, 0)));
+    }
+
+    #[test]
+    fn skipped_times_move_forward() {
+        // New York turned its clocks from 02:00 to 03:00 on 2024-03-10.
+        assert_eq!(resolve(&Tz::America__New_York, &local(3, 10, 3, 30)),
+                   resolve(&Tz::America__New_York, &local(3, 10, 2, 30)));
+    }
+
+    #[test]
+    fn repeated_times_use_the_first_occurrence() {
+        // New York turned its clocks from 02:00 back to 01:00 on 2024-11-03,
+        // 01:30 EDT is 05:30 UTC.
+        assert_eq!(1730611800, resolve(&Tz::America__New_York, &local(11, 3, 1, 30)));
+    }
+
+    #[test]
+    fn unknown_timezone() {
+        assert_eq!(Err(SmtpApiError::UnknownTimezone { index: 1, name: "Mars/Olympus".to_string() }),
+                   timestamps(&local(1, 15, 9, 0), &["Europe/Paris", "Mars/Olympus"]));
+    }
+}
@@ -0,0 +1,179 @@
+use std::error;
+use std::fmt;
+use rustc_serialize::json::ToJson;
+
+use super::Header;
+
+/// SendGrid accepts at most this many categories per message.
+pub const MAX_CATEGORIES: usize = 10;
+
+/// SendGrid ignores categories longer than this many bytes.
+pub const MAX_CATEGORY_LEN: usize = 255;
+
+/// SendGrid batch IDs are at most this many bytes long.
+pub const MAX_BATCH_ID_LEN: usize = 100;
+
+/// SendGrid drops unique arguments whose serialized size reaches this many bytes.
+pub const MAX_UNIQUE_ARGS_LEN: usize = 10_000;
+
+/// SendGrid accepts `spamcheck` max scores in this range.
+const SPAMCHECK_MAXSCORE: std::ops::RangeInclusive<f32> = 1.0..=10.0;
+
+/// A rule of the X-SMTPAPI format that a `Header` breaks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// A `to` entry is empty.
+    EmptyRecipient { index: usize },
+    /// A substitution column does not hold one value per recipient.
+    SubstitutionLength { tag: String, expected: usize, found: usize },
+    /// `send_each_at` does not hold one timestamp per recipient.
+    SendEachAtLength { expected: usize, found: usize },
+    /// Both `send_at` and `send_each_at` are set.
+    ScheduleConflict,
+    /// A category is the empty string.
+    EmptyCategory { index: usize },
+    /// A category is longer than `MAX_CATEGORY_LEN` bytes.
+    CategoryTooLong { index: usize, len: usize },
+    /// More than `MAX_CATEGORIES` categories are set.
+    TooManyCategories { count: usize },
+    /// `unique_args` serializes to `MAX_UNIQUE_ARGS_LEN` bytes or more.
+    UniqueArgsTooLarge { len: usize },
+    /// `asm_group_id` is not a positive number.
+    InvalidAsmGroupId(i32),
+    /// `batch_id` is empty, longer than `MAX_BATCH_ID_LEN` bytes or holds
+    /// characters other than ASCII letters, digits, `-` and `_`.
+    InvalidBatchId(String),
+    /// The `maxscore` setting of the `spamcheck` filter is not a number from 1 to 10.
+    InvalidSpamCheckMaxScore(String),
+}
+
+impl ValidationError {
+    /// Returns the wire name of the offending field.
+    pub fn field(&self) -> &'static str {
+        match *self {
+            ValidationError::EmptyRecipient { .. } => "to",
+            ValidationError::SubstitutionLength { .. } => "sub",
+            ValidationError::SendEachAtLength { .. } => "send_each_at",
+            ValidationError::ScheduleConflict => "send_at",
+            ValidationError::EmptyCategory { .. } |
+            ValidationError::CategoryTooLong { .. } |
+            ValidationError::TooManyCategories { .. } => "category",
+            ValidationError::UniqueArgsTooLarge { .. } => "unique_args",
+            ValidationError::InvalidAsmGroupId(_) => "asm_group_id",
+            ValidationError::InvalidBatchId(_) => "batch_id",
+            ValidationError::InvalidSpamCheckMaxScore(_) => "filters",
+        }
+    }
+
+    /// Returns the position of the offending entry, for list fields.
+    pub fn index(&self) -> Option<usize> {
+        match *self {
+            ValidationError::EmptyRecipient { index } |
+            ValidationError::EmptyCategory { index } |
+            ValidationError::CategoryTooLong { index, .. } => Some(index),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ValidationError::EmptyRecipient { index } => write!(f, "to[{}] is empty", index),
+            ValidationError::SubstitutionLength { ref tag, expected, found } => {
+                write!(f, "sub `{}` has {} values but there are {} recipients", tag, found, expected)
+            },
+            ValidationError::SendEachAtLength { expected, found } => {
+                write!(f, "send_each_at has {} timestamps but there are {} recipients", found, expected)
+            },
+            ValidationError::ScheduleConflict => write!(f, "send_at and send_each_at are both set"),
+            ValidationError::EmptyCategory { index } => write!(f, "category[{}] is empty", index),
+            ValidationError::CategoryTooLong { index, len } => {
+                write!(f, "category[{}] is {} bytes long, the limit is {}", index, len, MAX_CATEGORY_LEN)
+            },
+            ValidationError::TooManyCategories { count } => {
+                write!(f, "{} categories are set, the limit is {}", count, MAX_CATEGORIES)
+            },
+            ValidationError::UniqueArgsTooLarge { len } => {
+                write!(f, "unique_args is {} bytes long, it must stay under {}", len, MAX_UNIQUE_ARGS_LEN)
+            },
+            ValidationError::InvalidAsmGroupId(id) => write!(f, "asm_group_id {} is not a positive number", id),
+            ValidationError::InvalidBatchId(ref id) => write!(f, "batch_id `{}` is not a valid batch ID", id),
+            ValidationError::InvalidSpamCheckMaxScore(ref score) => {
+                write!(f, "spamcheck maxscore `{}` is not a number from 1 to 10", score)
+            },
+        }
+    }
+}
+
+impl error::Error for ValidationError {}
+
+pub fn validate(header: &Header) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let recipients = header.to.len();
+
+    for (index, email) in header.to.iter().enumerate() {
+        if email.trim().is_empty() {
+            errors.push(ValidationError::EmptyRecipient { index });
+        }
+    }
+
+    let mut tags: Vec<&String> = header.sub.keys().collect();
+    tags.sort();
+    for tag in tags {
+        let found = header.sub[tag].len();
+        if found != recipients {
+            errors.push(ValidationError::SubstitutionLength { tag: tag.clone(), expected: recipients, found });
+        }
+    }
+
+    if let Some(ref timestamps) = header.send_each_at {
+        if timestamps.len() != recipients {
+            errors.push(ValidationError::SendEachAtLength { expected: recipients, found: timestamps.len() });
+        }
+        if header.send_at.is_some() {
+            errors.push(ValidationError::ScheduleConflict);
+        }
+    }
+
+    if header.categories.len() > MAX_CATEGORIES {
+        errors.push(ValidationError::TooManyCategories { count: header.categories.len() });
+    }
+    for (index, category) in header.categories.iter().enumerate() {
+        if category.is_empty() {
+            errors.push(ValidationError::EmptyCategory { index });
+        } else if category.len() > MAX_CATEGORY_LEN {
+            errors.push(ValidationError::CategoryTooLong { index, len: category.len() });
+        }
+    }
+
+    if !header.unique_args.is_empty() {
+        let len = header.unique_args.to_json().to_string().len();
+        if len >= MAX_UNIQUE_ARGS_LEN {
+            errors.push(ValidationError::UniqueArgsTooLarge { len });
+        }
+    }
+
+    if let Some(id) = header.asm_group_id {
+        if id <= 0 {
+            errors.push(ValidationError::InvalidAsmGroupId(id));
+        }
+    }
+
+    if let Some(ref id) = header.batch_id {
+        let valid = !id.is_empty() && id.len() <= MAX_BATCH_ID_LEN &&
+            id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            errors.push(ValidationError::InvalidBatchId(id.clone()));
+        }
+    }
+
+    if let Some(score) = header.filters.get("spamcheck").and_then(|filter| filter.get("maxscore")) {
+        let valid = score.trim().parse::<f32>().map(|x| SPAMCHECK_MAXSCORE.contains(&x)).unwrap_or(false);
+        if !valid {
+            errors.push(ValidationError::InvalidSpamCheckMaxScore(score.to_owned()));
+        }
+    }
+
+    errors
+}
@@ -0,0 +1,29 @@
+#![cfg(feature = "idna")]
+
+extern crate smtpapi;
+
+use smtpapi::{AddressError, Header, IdnaForm, SmtpApiError};
+
+#[test]
+fn header_with_converted_domains() {
+    let mut header = Header::new();
+    header.add_tos(vec!["info@bücher.example", "\"Doe, John\" <john@Domain.com>", "user@[192.168.0.1]"])
+          .add_filter("bcc", "email", "archive@münchen.de");
+
+    header.convert_domains(IdnaForm::ALabel).unwrap();
+    assert_eq!(["info@xn--bcher-kva.example", "\"Doe, John\" <john@domain.com>", "user@[192.168.0.1]"], header.tos());
+    assert_eq!(Some("archive@xn--mnchen-3ya.de"), header.filter("bcc").and_then(|filter| filter.get("email")));
+
+    header.convert_domains(IdnaForm::ULabel).unwrap();
+    assert_eq!(["info@bücher.example", "\"Doe, John\" <john@domain.com>", "user@[192.168.0.1]"], header.tos());
+    assert_eq!(Some("archive@münchen.de"), header.filter("bcc").and_then(|filter| filter.get("email")));
+
+    header.add_to("email@xn--a.example")
+          .add_filter("bcc", "email", "archive");
+    let errors = header.convert_domains(IdnaForm::ALabel).unwrap_err();
+    assert_eq!(vec![
+        SmtpApiError::InvalidAddress { index: 3, email: "email@xn--a.example".to_owned(), reason: AddressError::InvalidIdn },
+        SmtpApiError::InvalidBccAddress { email: "archive".to_owned(), reason: AddressError::MissingAt },
+    ], errors);
+    assert_eq!("info@bücher.example", header.tos()[0]);
+}
@@ -1,9 +1,10 @@
 extern crate smtpapi;
 extern crate rustc_serialize;
-extern crate time;
 
-use smtpapi::{Header, Filter};
+use smtpapi::{AddressError, DedupPolicy, EmailAddress, Header, Mailbox, HeaderBuilder, Change, Filter, Encoding, MergePolicy, MergeStrategy, Occurrence, ParseError, Recipient, Schedule, SendTime, SmtpApiError, Spread, Suppressions, ValidationError, MAX_SCHEDULE_SECS};
+use smtpapi::apps::{BypassListManagement, OpenTrack, SpamCheck, SubscriptionTrack, Templates};
 use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
 
 #[test]
 fn version() {
@@ -168,11 +169,10 @@ fn header_with_asm_group_id_to_json_string() {
 }
 
 #[test]
+#[allow(deprecated)]
 fn header_with_send_at_to_json_string() {
-    let x = time::now_utc().to_timespec().sec as i64;
-    let mut timestamps = Vec::new();
-    timestamps.push(x);
-    timestamps.push(x);
+    let x = SystemTime::now().to_timestamp();
+    let timestamps = vec![x, x];
 
     let mut header = Header::new();
     header.set_send_each_at(timestamps);
@@ -181,14 +181,15 @@ fn header_with_send_at_to_json_string() {
 
     let mut s = "{\"send_at\":".to_string();
     s.push_str(&format!("{:?}", x));
-    s.push_str("}");
+    s.push('}');
 
     assert_eq!(s, header.to_json_string());
 }
 
 #[test]
+#[allow(deprecated)]
 fn header_with_send_each_at_to_json_string() {
-    let x = time::now_utc().to_timespec().sec as i64;
+    let x = SystemTime::now().to_timestamp();
     let y = x + 50;
     let z = x + 40;
     let timestamps = vec![x, y];
@@ -202,9 +203,9 @@ fn header_with_send_each_at_to_json_string() {
 
     let mut s = "{\"send_each_at\":[".to_string();
     s.push_str(&format!("{:?}", x));
-    s.push_str(",");
+    s.push(',');
     s.push_str(&format!("{:?}", y));
-    s.push_str(",");
+    s.push(',');
     s.push_str(&format!("{:?}", z));
     s.push_str("]}");
 
@@ -212,8 +213,9 @@ fn header_with_send_each_at_to_json_string() {
 }
 
 #[test]
+#[allow(deprecated)]
 fn header_with_add_send_each_at_to_json_string() {
-    let x = time::now_utc().to_timespec().sec as i64;
+    let x = SystemTime::now().to_timestamp();
     let y = x + 50;
     let z = x + 40;
 
@@ -227,11 +229,756 @@ fn header_with_add_send_each_at_to_json_string() {
 
     let mut s = "{\"send_each_at\":[".to_string();
     s.push_str(&format!("{:?}", x));
-    s.push_str(",");
+    s.push(',');
     s.push_str(&format!("{:?}", y));
-    s.push_str(",");
+    s.push(',');
     s.push_str(&format!("{:?}", z));
     s.push_str("]}");
 
     assert_eq!(s, header.to_json_string());
 }
+
+#[test]
+fn header_from_json_string() {
+    let json = "{\"asm_group_id\":12,\"category\":[\"welcome\"],\"filters\":{\"clicktrack\":{\"settings\":{\"enabled\":\"1\"}}},\"ip_pool\":\"pool_1\",\"section\":{\"-top-\":\"foobar\"},\"send_each_at\":[1453213937,1453213939],\"sub\":{\"-name-\":[\"foo\",\"bar\"]},\"to\":[\"foo1@domain.com\",\"foo2@domain.com\"],\"unique_args\":{\"-arg1-\":\"value1\"}}";
+    let header = Header::from_json_str(json).unwrap();
+
+    assert_eq!(json, header.to_json_string());
+    assert_eq!(json, json.parse::<Header>().unwrap().to_json_string());
+}
+
+#[test]
+fn header_from_json_string_with_loose_values() {
+    let header = Header::from_json_str("{\"category\":\"welcome\",\"filters\":{\"opentrack\":{\"settings\":{\"enable\":1}}}}").unwrap();
+
+    assert_eq!("{\"category\":[\"welcome\"],\"filters\":{\"opentrack\":{\"settings\":{\"enable\":\"1\"}}}}", header.to_json_string());
+}
+
+#[test]
+fn header_from_malformed_json_string() {
+    assert!(matches!(Header::from_json_str("{\"to\":"), Err(ParseError::Syntax(_))));
+    assert!(matches!(Header::from_json_str("[]"), Err(ParseError::NotAnObject)));
+    assert!(matches!(Header::from_json_str("{\"categories\":[]}"), Err(ParseError::UnknownField(ref f)) if f == "categories"));
+    assert!(matches!(Header::from_json_str("{\"filters\":{\"footer\":{\"enabled\":\"1\"}}}"), Err(ParseError::UnknownField(ref f)) if f == "filters.footer.enabled"));
+
+    match Header::from_json_str("{\"sub\":{\"-name-\":[\"foo\",2]}}") {
+        Err(e) => assert_eq!("field `sub.-name-.1` must be a string", e.to_string()),
+        Ok(_) => panic!("expected an error"),
+    }
+}
+
+#[test]
+fn header_validate() {
+    let mut header = Header::new();
+    header.add_tos(vec!["foo1@domain.com", "foo2@domain.com"])
+          .add_substitutions("-name-", vec!["foo", "bar"])
+          .add_send_each_at(1453213937)
+          .add_send_each_at(1453213939)
+          .add_category("welcome")
+          .set_asm_group_id(12);
+
+    assert_eq!(Ok(()), header.validate());
+}
+
+#[test]
+fn header_validate_reports_every_error() {
+    let mut header = Header::new();
+    header.add_tos(vec!["foo1@domain.com", ""])
+          .add_substitution("-name-", "foo")
+          .add_send_each_at(1453213937)
+          .add_categories(vec!["welcome", ""])
+          .set_asm_group_id(-1);
+
+    let errors = header.validate().unwrap_err();
+    assert_eq!(vec![ValidationError::EmptyRecipient { index: 1 },
+                    ValidationError::SubstitutionLength { tag: "-name-".to_string(), expected: 2, found: 1 },
+                    ValidationError::SendEachAtLength { expected: 2, found: 1 },
+                    ValidationError::EmptyCategory { index: 1 },
+                    ValidationError::InvalidAsmGroupId(-1)], errors);
+
+    assert_eq!("category", errors[3].field());
+    assert_eq!(Some(1), errors[3].index());
+    assert_eq!("sub `-name-` has 1 values but there are 2 recipients", errors[1].to_string());
+}
+
+#[test]
+fn header_validate_schedule_conflict() {
+    let header = Header::from_json_str("{\"send_at\":1453213937,\"send_each_at\":[1453213937],\"to\":[\"foo@domain.com\"]}").unwrap();
+
+    assert_eq!(Err(vec![ValidationError::ScheduleConflict]), header.validate());
+}
+
+#[test]
+fn header_validate_spamcheck_maxscore() {
+    let mut header = Header::new();
+
+    for &score in &[1.0, 5.5, 10.0] {
+        header.set_app(SpamCheck::new().maxscore(score));
+        assert_eq!(Ok(()), header.validate());
+    }
+
+    for &score in &[0.5, -3.0, 10.5, f32::NAN] {
+        header.set_app(SpamCheck::new().maxscore(score));
+        assert_eq!(Err(vec![ValidationError::InvalidSpamCheckMaxScore(score.to_string())]), header.validate());
+    }
+
+    header.add_filter("spamcheck", "maxscore", "high");
+    let errors = header.validate().unwrap_err();
+    assert_eq!("filters", errors[0].field());
+    assert_eq!("spamcheck maxscore `high` is not a number from 1 to 10", errors[0].to_string());
+}
+
+#[test]
+fn header_to_folded_header_line() {
+    let mut header = Header::new();
+    let emails: Vec<String> = (0..200).map(|i| format!("recipient{}@domain.com", i)).collect();
+    header.add_tos(emails);
+
+    let line = header.to_header_line().unwrap();
+    assert!(line.starts_with("X-SMTPAPI: {\"to\":["));
+    assert!(line.split("\r\n").count() > 1);
+    assert!(line.split("\r\n").all(|l| l.len() <= smtpapi::MAX_LINE_LEN));
+    assert!(line.split("\r\n").skip(1).all(|l| l.starts_with(' ')));
+    assert_eq!(header.to_json_string(), line["X-SMTPAPI: ".len()..].replace("\r\n ", ""));
+
+    let mut message = Vec::new();
+    header.write_header(&mut message).unwrap();
+    assert_eq!(format!("{}\r\n", line).into_bytes(), message);
+}
+
+#[test]
+fn header_with_unfoldable_substitution_value() {
+    let mut header = Header::new();
+    let long_value = "x".repeat(smtpapi::MAX_LINE_LEN);
+    header.add_recipient("email@domain.com", vec![("-body-", long_value.as_str())]).unwrap();
+
+    match header.to_header_line() {
+        Err(SmtpApiError::LineTooLong { max, .. }) => assert_eq!(smtpapi::MAX_LINE_LEN, max),
+        other => panic!("unexpected {:?}", other),
+    }
+
+    let mut message = Vec::new();
+    let error = header.write_header(&mut message).unwrap_err();
+    assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+    assert!(message.is_empty());
+}
+
+#[test]
+fn header_with_ascii_encoding_to_json_string() {
+    let mut header = Header::new();
+
+    header.add_category("天破活殺")
+          .add_category("á")
+          .add_filter("footer", "text/plain", "📧")
+          .set_encoding(Encoding::Ascii);
+
+    let json = header.to_json_string();
+    assert_eq!("{\"category\":[\"\\u5929\\u7834\\u6d3b\\u6bba\",\"\\u00e1\"],\"filters\":{\"footer\":{\"settings\":{\"text/plain\":\"\\ud83d\\udce7\"}}}}", json);
+    assert!(header.to_header_line().unwrap().is_ascii());
+
+    header.set_encoding(Encoding::Utf8);
+    assert_eq!(header.to_json_string(), Header::from_json_str(&json).unwrap().to_json_string());
+}
+
+#[test]
+fn header_with_apps_to_json_string() {
+    let mut header = Header::new();
+
+    header.set_app(OpenTrack::new().replace("%open%"))
+          .set_app(BypassListManagement::new().enable(false))
+          .set_app(SpamCheck::new().maxscore(3.5).url("http://domain.com/spam"))
+          .set_app(Templates::new().template_id("5997fcf6-2b9f-484d-acd5-7e9a99f0dc1f"));
+
+    assert_eq!("{\"filters\":{\"bypass_list_management\":{\"settings\":{\"enable\":\"0\"}},\"opentrack\":{\"settings\":{\"enable\":\"1\",\"replace\":\"%open%\"}},\"spamcheck\":{\"settings\":{\"enable\":\"1\",\"maxscore\":\"3.5\",\"url\":\"http://domain.com/spam\"}},\"templates\":{\"settings\":{\"enable\":\"1\",\"template_id\":\"5997fcf6-2b9f-484d-acd5-7e9a99f0dc1f\"}}}}", header.to_json_string());
+}
+
+#[test]
+fn header_with_subscription_track_app_to_json_string() {
+    let mut header = Header::new();
+    let mut app = SubscriptionTrack::new();
+    app.text_html("<p><% Unsubscribe %></p>")
+       .text_plain("Unsubscribe: <% %>")
+       .landing("http://domain.com/bye");
+
+    header.set_filter("subscriptiontrack", Filter::from(app));
+
+    assert_eq!("{\"filters\":{\"subscriptiontrack\":{\"settings\":{\"enable\":\"1\",\"landing\":\"http://domain.com/bye\",\"text/html\":\"<p><% Unsubscribe %></p>\",\"text/plain\":\"Unsubscribe: <% %>\"}}}}", header.to_json_string());
+}
+
+#[test]
+fn header_with_recipients_to_json_string() {
+    let mut header = Header::new();
+
+    header.add_to("foo1@domain.com")
+          .add_substitution("-name-", "foo1")
+          .add_to("foo2@domain.com")
+          .add_recipient("foo3@domain.com", vec![("-city-", "Paris")]).unwrap()
+          .add_recipient("foo4@domain.com", vec![("-name-", "foo4"), ("-city-", "Rome")]).unwrap();
+
+    assert_eq!("{\"sub\":{\"-city-\":[\"\",\"\",\"Paris\",\"Rome\"],\"-name-\":[\"foo1\",\"\",\"\",\"foo4\"]},\"to\":[\"foo1@domain.com\",\"foo2@domain.com\",\"foo3@domain.com\",\"foo4@domain.com\"]}", header.to_json_string());
+    assert_eq!(Ok(()), header.validate());
+
+    let recipients: Vec<Recipient> = header.recipients().collect();
+    assert_eq!(4, recipients.len());
+    assert_eq!("foo3@domain.com", recipients[2].email());
+    assert_eq!(vec![("-city-", "Rome"), ("-name-", "foo4")], recipients[3].substitutions());
+    assert_eq!(None, recipients[0].send_at());
+    assert!(header.recipient(4).is_none());
+}
+
+#[test]
+fn header_add_recipient_rejects_longer_columns() {
+    let mut header = Header::new();
+
+    header.add_to("foo1@domain.com")
+          .add_substitutions("-city-", vec!["Paris", "Rome"]);
+
+    let error = header.add_recipient("foo2@domain.com", vec![("-name-", "foo2")]).unwrap_err();
+    assert_eq!(SmtpApiError::MisalignedSubstitution { tag: "-city-".to_string(), expected: 1, found: 2 }, error);
+    assert_eq!("{\"sub\":{\"-city-\":[\"Paris\",\"Rome\"]},\"to\":[\"foo1@domain.com\"]}", header.to_json_string());
+}
+
+#[test]
+fn header_split_by_recipients() {
+    let mut header = Header::new();
+
+    for i in 0..5 {
+        header.add_recipient(format!("foo{}@domain.com", i), vec![("-name-", format!("foo{}", i))]).unwrap()
+              .add_send_each_at(1453213930 + i);
+    }
+    header.add_category("welcome")
+          .add_section("-top-", "foobar")
+          .add_unique_arg("-arg1-", "value1")
+          .add_filter("clicktrack", "enable", "1")
+          .set_asm_group_id(12)
+          .set_ip_pool("pool_1");
+
+    let parts = header.split(2);
+    assert_eq!(3, parts.len());
+    assert_eq!("{\"asm_group_id\":12,\"category\":[\"welcome\"],\"filters\":{\"clicktrack\":{\"settings\":{\"enable\":\"1\"}}},\"ip_pool\":\"pool_1\",\"section\":{\"-top-\":\"foobar\"},\"send_each_at\":[1453213932,1453213933],\"sub\":{\"-name-\":[\"foo2\",\"foo3\"]},\"to\":[\"foo2@domain.com\",\"foo3@domain.com\"],\"unique_args\":{\"-arg1-\":\"value1\"}}", parts[1].to_json_string());
+    assert_eq!(vec!["foo4@domain.com"], parts[2].recipients().map(|r| r.email()).collect::<Vec<&str>>());
+    assert!(parts.iter().all(|part| part.validate().is_ok()));
+
+    assert_eq!(1, Header::new().split(2).len());
+}
+
+#[test]
+fn header_split_by_size() {
+    let mut header = Header::new();
+
+    for i in 0..50 {
+        header.add_recipient(format!("foo{}@domain.com", i), vec![("-name-", "天".repeat(i))]).unwrap()
+              .add_send_each_at(1453213930 + i as i64);
+    }
+    header.add_category("welcome")
+          .set_encoding(Encoding::Ascii);
+
+    let parts = header.split_by_size(1000).unwrap();
+    assert!(parts.len() > 1);
+    assert!(parts.iter().all(|part| part.to_json_string().len() <= 1000 && part.validate().is_ok()));
+
+    let emails: Vec<String> = parts.iter().flat_map(|part| part.recipients().map(|r| r.email().to_string()).collect::<Vec<String>>()).collect();
+    assert_eq!(header.recipients().map(|r| r.email().to_string()).collect::<Vec<String>>(), emails);
+
+    // Each part is full: adding the next recipient would exceed the budget.
+    for pair in parts.windows(2) {
+        let mut bigger = pair[0].clone();
+        let next = pair[1].recipient(0).unwrap();
+        bigger.add_recipient(next.email(), next.substitutions()).unwrap()
+              .add_send_each_at(next.send_at().unwrap());
+        assert!(bigger.to_json_string().len() > 1000);
+    }
+
+    let oversized = header.split_by_size(250).unwrap_err();
+    assert!(!oversized.is_empty());
+    assert_eq!(50, oversized[0].index + oversized.len());
+    assert!(oversized.iter().all(|r| r.len > 250));
+}
+
+#[test]
+fn header_to_canonical_string() {
+    let mut header = Header::new();
+    header.add_recipient("foo2@domain.com", vec![("-name-", "foo2"), ("-city-", "Paris")]).unwrap()
+          .add_recipient("foo1@domain.com", vec![("-city-", "Rome"), ("-name-", "foo1")]).unwrap()
+          .add_categories(vec!["天破活殺", "welcome", "accounts", "welcome"])
+          .add_filter("footer", "text/plain", "bye")
+          .add_filter("clicktrack", "enable", "1")
+          .add_filter("footer", "enable", "1")
+          .set_encoding(Encoding::Ascii);
+
+    let golden = "{\"category\":[\"accounts\",\"welcome\",\"天破活殺\"],\"filters\":{\"clicktrack\":{\"settings\":{\"enable\":\"1\"}},\"footer\":{\"settings\":{\"enable\":\"1\",\"text/plain\":\"bye\"}}},\"sub\":{\"-city-\":[\"Paris\",\"Rome\"],\"-name-\":[\"foo2\",\"foo1\"]},\"to\":[\"foo2@domain.com\",\"foo1@domain.com\"]}";
+    assert_eq!(golden, header.to_canonical_string());
+    assert_eq!(0x3787_58b1_b762_9117, header.canonical_hash());
+
+    let copy = Header::from_json_str(&header.to_json_string()).unwrap();
+    assert_eq!(header.canonical_hash(), copy.canonical_hash());
+
+    header.set_tos(vec!["foo1@domain.com", "foo2@domain.com"]);
+    assert!(header.canonical_hash() != copy.canonical_hash());
+}
+
+#[test]
+fn header_accessors() {
+    let header = Header::from_json_str("{\"asm_group_id\":12,\"category\":[\"welcome\"],\"filters\":{\"clicktrack\":{\"settings\":{\"enable\":\"1\"}},\"footer\":{\"settings\":{\"enable\":\"1\",\"text/plain\":\"bye\"}}},\"ip_pool\":\"pool_1\",\"section\":{\"-top-\":\"foobar\"},\"send_each_at\":[1453213937,1453213939],\"sub\":{\"-name-\":[\"foo\",\"bar\"]},\"to\":[\"foo1@domain.com\",\"foo2@domain.com\"],\"unique_args\":{\"-arg1-\":\"value1\"}}").unwrap();
+
+    assert_eq!(["foo1@domain.com", "foo2@domain.com"], header.tos());
+    assert_eq!(vec![("-name-", &["foo".to_string(), "bar".to_string()][..])], header.substitutions().collect::<Vec<_>>());
+    assert_eq!(None, header.substitution("-city-"));
+    assert_eq!(vec![("-top-", "foobar")], header.sections().collect::<Vec<_>>());
+    assert_eq!(["welcome"], header.categories());
+    assert_eq!(Some("value1"), header.unique_arg("-arg1-"));
+    assert_eq!(vec![("footer", vec![("enable", "1"), ("text/plain", "bye")])],
+               header.filters().skip(1).map(|(name, filter)| (name, filter.settings().collect::<Vec<_>>())).collect::<Vec<_>>());
+    assert_eq!(Some("bye"), header.filter("footer").and_then(|filter| filter.get("text/plain")));
+    assert_eq!(Some(12), header.asm_group_id());
+    assert_eq!(Some("pool_1"), header.ip_pool());
+    assert_eq!(None, header.send_at());
+    assert_eq!(Some(&[1453213937, 1453213939][..]), header.send_each_at());
+    assert_eq!(Encoding::Utf8, header.encoding());
+}
+
+#[test]
+fn header_remove_recipients_keeps_alignment() {
+    let mut header = Header::new();
+
+    for (i, email) in ["foo1@domain.com", "foo2@domain.com", "foo1@domain.com", "foo3@domain.com"].iter().enumerate() {
+        header.add_recipient(*email, vec![("-name-", format!("name{}", i))]).unwrap()
+              .add_send_each_at(1453213930 + i as i64);
+    }
+
+    header.remove_to("foo1@domain.com");
+    assert_eq!("{\"send_each_at\":[1453213931,1453213933],\"sub\":{\"-name-\":[\"name1\",\"name3\"]},\"to\":[\"foo2@domain.com\",\"foo3@domain.com\"]}", header.to_json_string());
+
+    header.remove_recipient(1);
+    assert_eq!("{\"send_each_at\":[1453213931],\"sub\":{\"-name-\":[\"name1\"]},\"to\":[\"foo2@domain.com\"]}", header.to_json_string());
+    assert_eq!(Ok(()), header.validate());
+
+    header.remove_recipient(1);
+    assert_eq!("{\"send_each_at\":[1453213931],\"sub\":{\"-name-\":[\"name1\"]},\"to\":[\"foo2@domain.com\"]}", header.to_json_string());
+
+    header.clear_tos();
+    assert_eq!("{}", header.to_json_string());
+    assert_eq!(None, header.send_each_at());
+}
+
+#[test]
+fn header_dedup_recipients_keeps_alignment() {
+    let emails = ["foo1@domain.com", "Foo1@Domain.com", "foo2@domain.com", "John <foo1@DOMAIN.COM>"];
+    let mut header = Header::new();
+
+    for (i, email) in emails.iter().enumerate() {
+        header.add_recipient(*email, vec![("-name-", format!("name{}", i))]).unwrap()
+              .add_send_each_at(1453213930 + i as i64);
+    }
+
+    let mut first = header.clone();
+    first.dedup_recipients(DedupPolicy::default());
+    assert_eq!("{\"send_each_at\":[1453213930,1453213931,1453213932],\"sub\":{\"-name-\":[\"name0\",\"name1\",\"name2\"]},\"to\":[\"foo1@domain.com\",\"Foo1@Domain.com\",\"foo2@domain.com\"]}", first.to_json_string());
+
+    header.dedup_recipients(DedupPolicy { keep: Occurrence::Last, fold_local_part: true });
+    assert_eq!("{\"send_each_at\":[1453213932,1453213933],\"sub\":{\"-name-\":[\"name2\",\"name3\"]},\"to\":[\"foo2@domain.com\",\"John <foo1@DOMAIN.COM>\"]}", header.to_json_string());
+    assert_eq!(Ok(()), header.validate());
+}
+
+#[test]
+fn header_apply_suppressions_keeps_alignment() {
+    let path = std::env::temp_dir().join(format!("smtpapi-suppressions-{}.csv", std::process::id()));
+    std::fs::write(&path, "email,reason\nfoo2@domain.com,bounce\n*@Example.org,unsubscribe\n").unwrap();
+    let suppressions = Suppressions::from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let mut header = Header::new();
+    for (i, email) in ["foo1@domain.com", "Foo2@DOMAIN.com", "foo3@example.org", "foo4@domain.com"].iter().enumerate() {
+        header.add_recipient(*email, vec![("-name-", format!("name{}", i))]).unwrap()
+              .add_send_each_at(1453213930 + i as i64);
+    }
+
+    assert_eq!(vec!["Foo2@DOMAIN.com", "foo3@example.org"], header.apply_suppressions(&suppressions));
+    assert_eq!("{\"send_each_at\":[1453213930,1453213933],\"sub\":{\"-name-\":[\"name0\",\"name3\"]},\"to\":[\"foo1@domain.com\",\"foo4@domain.com\"]}", header.to_json_string());
+    assert!(header.apply_suppressions(&suppressions).is_empty());
+    assert!(Suppressions::from_file(&path).is_err());
+}
+
+#[test]
+fn header_remove_and_unset_fields() {
+    let mut header = Header::from_json_str("{\"asm_group_id\":12,\"category\":[\"welcome\",\"accounts\",\"welcome\"],\"filters\":{\"clicktrack\":{\"settings\":{\"enable\":\"1\"}},\"footer\":{\"settings\":{\"enable\":\"1\",\"text/plain\":\"bye\"}}},\"ip_pool\":\"pool_1\",\"section\":{\"-top-\":\"foobar\"},\"send_at\":1453213937,\"sub\":{\"-name-\":[\"foo\"]},\"unique_args\":{\"-arg1-\":\"value1\"}}").unwrap();
+
+    header.remove_category("welcome")
+          .remove_filter("clicktrack")
+          .remove_section("-top-")
+          .remove_unique_arg("-arg1-")
+          .remove_substitution("-name-");
+    header.filter_mut("footer").unwrap().remove_setting("text/plain");
+    assert_eq!("{\"asm_group_id\":12,\"category\":[\"accounts\"],\"filters\":{\"footer\":{\"settings\":{\"enable\":\"1\"}}},\"ip_pool\":\"pool_1\",\"send_at\":1453213937}", header.to_json_string());
+
+    header.unset_asm_group_id()
+          .unset_ip_pool()
+          .unset_send_at()
+          .clear_categories()
+          .clear_filters();
+    assert_eq!("{}", header.to_json_string());
+}
+
+#[test]
+fn header_merge_layers_overrides_over_base() {
+    let mut base = Header::new();
+    base.set_substitution_default("friend")
+        .add_recipient("foo1@domain.com", vec![("-name-", "foo1")]).unwrap()
+        .add_category("newsletter")
+        .add_section("-top-", "foobar")
+        .add_unique_arg("-arg1-", "value1")
+        .add_filter("footer", "enable", "1")
+        .add_filter("footer", "text/plain", "bye")
+        .set_asm_group_id(12)
+        .set_ip_pool("pool_1");
+
+    let mut campaign = Header::new();
+    campaign.add_recipient("foo2@domain.com", vec![("-city-", "Paris")]).unwrap()
+            .add_category("spring")
+            .add_unique_arg("-arg1-", "value2")
+            .add_filter("footer", "text/plain", "ciao")
+            .add_filter("clicktrack", "enable", "1")
+            .set_asm_group_id(34);
+
+    let mut merged = base.clone();
+    merged.merge(&campaign, MergeStrategy::default()).unwrap();
+    assert_eq!("{\"asm_group_id\":34,\"category\":[\"newsletter\",\"spring\"],\"filters\":{\"clicktrack\":{\"settings\":{\"enable\":\"1\"}},\"footer\":{\"settings\":{\"enable\":\"1\",\"text/plain\":\"ciao\"}}},\"ip_pool\":\"pool_1\",\"section\":{\"-top-\":\"foobar\"},\"sub\":{\"-city-\":[\"friend\",\"Paris\"],\"-name-\":[\"foo1\",\"friend\"]},\"to\":[\"foo1@domain.com\",\"foo2@domain.com\"],\"unique_args\":{\"-arg1-\":\"value2\"}}", merged.to_json_string());
+    assert!(merged.validate().is_ok());
+
+    let mut replaced = base.clone();
+    replaced.merge(&campaign, MergeStrategy::all(MergePolicy::Replace)).unwrap();
+    assert_eq!(["foo2@domain.com"], replaced.tos());
+    assert_eq!(["spring"], replaced.categories());
+    assert_eq!(None, replaced.filter("footer").unwrap().get("enable"));
+    assert_eq!(Some("foobar"), replaced.section("-top-"));
+
+    let mut kept = base.clone();
+    kept.merge(&campaign, MergeStrategy::all(MergePolicy::Keep)).unwrap();
+    assert_eq!(base.to_json_string(), kept.to_json_string());
+}
+
+#[test]
+fn header_merge_schedules() {
+    let mut base = Header::new();
+    base.add_to("foo1@domain.com")
+        .add_send_each_at(1453213937);
+
+    let mut campaign = Header::new();
+    campaign.add_to("foo2@domain.com")
+            .add_send_each_at(1453213939);
+
+    base.merge(&campaign, MergeStrategy::default()).unwrap();
+    assert_eq!(Some(&[1453213937, 1453213939][..]), base.send_each_at());
+
+    let mut later = Header::new();
+    later.set_schedule(Schedule::At(1453213999));
+    base.merge(&later, MergeStrategy::default()).unwrap();
+    assert_eq!(Some(1453213999), base.send_at());
+    assert_eq!(None, base.send_each_at());
+}
+
+#[test]
+fn header_merge_pads_send_each_at_with_send_at() {
+    let mut base = Header::new();
+    base.add_to("foo1@domain.com")
+        .set_schedule(Schedule::At(1453213900));
+
+    let mut campaign = Header::new();
+    campaign.add_tos(vec!["foo2@domain.com", "foo3@domain.com"])
+            .set_schedule(Schedule::EachAt(vec![1453213937, 1453213939]));
+
+    let mut merged = base.clone();
+    merged.merge(&campaign, MergeStrategy::default()).unwrap();
+    assert_eq!("{\"send_each_at\":[1453213900,1453213937,1453213939],\"to\":[\"foo1@domain.com\",\"foo2@domain.com\",\"foo3@domain.com\"]}", merged.to_json_string());
+    assert_eq!(Ok(()), merged.validate());
+
+    let mut merged = campaign.clone();
+    base.set_schedule(Schedule::At(1453213999));
+    merged.merge(&base, MergeStrategy::default()).unwrap();
+    assert_eq!(Some(&[1453213937, 1453213939, 1453213999][..]), merged.send_each_at());
+    assert_eq!(None, merged.send_at());
+    assert_eq!(Ok(()), merged.validate());
+}
+
+#[test]
+fn header_merge_rejects_misaligned_recipients() {
+    let mut base = Header::new();
+    base.add_to("a@d.com");
+
+    let mut campaign = Header::new();
+    campaign.add_to("b@d.com")
+            .add_send_each_at(1000);
+
+    let mut merged = base.clone();
+    assert_eq!(SmtpApiError::MisalignedSendEachAt { expected: 2, found: 1 }, merged.merge(&campaign, MergeStrategy::default()).unwrap_err());
+    assert_eq!(base.to_json_string(), merged.to_json_string());
+
+    let mut merged = campaign.clone();
+    assert_eq!(SmtpApiError::MisalignedSendEachAt { expected: 2, found: 1 }, merged.merge(&base, MergeStrategy::default()).unwrap_err());
+    assert_eq!(campaign.to_json_string(), merged.to_json_string());
+
+    base.add_substitutions("-city-", vec!["Paris", "Rome"]);
+    campaign.set_schedule(Schedule::At(1000));
+    let mut merged = base.clone();
+    assert_eq!(SmtpApiError::MisalignedSubstitution { tag: "-city-".to_string(), expected: 1, found: 2 }, merged.merge(&campaign, MergeStrategy::default()).unwrap_err());
+    assert_eq!(base.to_json_string(), merged.to_json_string());
+}
+
+#[test]
+fn header_diff() {
+    let mut old = Header::new();
+    old.add_recipient("foo1@domain.com", vec![("-name-", "foo1")]).unwrap()
+       .add_recipient("foo2@domain.com", vec![("-name-", "foo2")]).unwrap()
+       .add_recipient("foo3@domain.com", vec![("-name-", "foo3")]).unwrap()
+       .add_categories(vec!["welcome", "accounts"])
+       .add_filter("footer", "enable", "1")
+       .add_filter("footer", "text/plain", "bye")
+       .set_schedule(Schedule::At(1453213937));
+
+    assert!(old.diff(&old.clone()).is_empty());
+
+    let mut new = Header::new();
+    new.add_recipient("foo1@domain.com", vec![("-name-", "Foo")]).unwrap()
+       .add_recipient("foo3@domain.com", vec![("-name-", "foo3")]).unwrap()
+       .add_recipient("foo4@domain.com", vec![("-name-", "foo4")]).unwrap()
+       .add_categories(vec!["accounts", "spring"])
+       .add_filter("footer", "enable", "1")
+       .add_filter("clicktrack", "enable", "1")
+       .set_schedule(Schedule::At(1453213999));
+
+    let changes = old.diff(&new);
+    assert_eq!(vec![
+        Change::RecipientRemoved { index: 1, email: "foo2@domain.com".to_string() },
+        Change::RecipientAdded { index: 2, email: "foo4@domain.com".to_string() },
+        Change::SubstitutionChanged { tag: "-name-".to_string(), index: 0, email: "foo1@domain.com".to_string(), old: Some("foo1".to_string()), new: Some("Foo".to_string()) },
+        Change::CategoryRemoved("welcome".to_string()),
+        Change::CategoryAdded("spring".to_string()),
+        Change::FilterSettingChanged { filter: "clicktrack".to_string(), setting: "enable".to_string(), old: None, new: Some("1".to_string()) },
+        Change::FilterSettingChanged { filter: "footer".to_string(), setting: "text/plain".to_string(), old: Some("bye".to_string()), new: None },
+        Change::SendAtChanged { old: Some(1453213937), new: Some(1453213999) },
+    ], changes);
+
+    assert_eq!(vec![
+        "to[1] `foo2@domain.com` removed",
+        "to[2] `foo4@domain.com` added",
+        "sub `-name-` of to[0] `foo1@domain.com` changed from \"foo1\" to \"Foo\"",
+        "category `welcome` removed",
+        "category `spring` added",
+        "filters `clicktrack` setting `enable` changed from unset to \"1\"",
+        "filters `footer` setting `text/plain` changed from \"bye\" to unset",
+        "send_at changed from 1453213937 to 1453213999",
+    ], changes.iter().map(|change| change.to_string()).collect::<Vec<String>>());
+    assert_eq!("sub", changes[2].field());
+}
+
+#[test]
+fn header_diff_on_large_recipient_lists() {
+    let emails: Vec<String> = (0..100_000).map(|i| format!("foo{}@domain.com", i)).collect();
+
+    let mut old = Header::new();
+    old.add_tos(emails.clone());
+
+    let mut new = Header::new();
+    new.add_to("first@domain.com")
+       .add_tos(emails.iter().rev().cloned().collect());
+    new.remove_to("foo50000@domain.com");
+
+    assert_eq!(vec![
+        Change::RecipientRemoved { index: 50_000, email: "foo50000@domain.com".to_string() },
+        Change::RecipientAdded { index: 0, email: "first@domain.com".to_string() },
+    ], old.diff(&new));
+}
+
+#[test]
+fn header_builder_reports_errors() {
+    let header = HeaderBuilder::new()
+        .add_recipient("foo1@domain.com", vec![("-name-", "foo1")])
+        .add_recipient("foo2@domain.com", vec![("-name-", "foo2")])
+        .set_send_each_at(vec![1453213937, 1453213939])
+        .set_asm_group_id(12)
+        .build()
+        .unwrap();
+    assert_eq!("{\"asm_group_id\":12,\"send_each_at\":[1453213937,1453213939],\"sub\":{\"-name-\":[\"foo1\",\"foo2\"]},\"to\":[\"foo1@domain.com\",\"foo2@domain.com\"]}", header.to_json_string());
+
+    let mut builder = HeaderBuilder::new();
+    builder.add_tos(vec!["foo1@domain.com", "foo2@domain.com"])
+           .add_substitution("-name-", "foo1");
+    assert_eq!(Err(SmtpApiError::MisalignedSubstitution { tag: "-name-".to_string(), expected: 2, found: 1 }), builder.build().map(|_| ()));
+
+    builder.add_substitution("-name-", "foo2")
+           .set_send_each_at(vec![1453213937, 1453213939])
+           .set_send_at(1453213937);
+    assert_eq!(Err(SmtpApiError::ScheduleConflict), builder.build().map(|_| ()));
+
+    let mut builder = HeaderBuilder::from(Header::from_json_str("{\"to\":[\"foo1@domain.com\"],\"asm_group_id\":0}").unwrap());
+    let error = builder.build().unwrap_err();
+    assert_eq!(SmtpApiError::Invalid(ValidationError::InvalidAsmGroupId(0)), error);
+    assert!(std::error::Error::source(&error).is_some());
+
+    builder.set_asm_group_id(12)
+           .set_max_size(20);
+    let error = builder.build().unwrap_err();
+    assert_eq!(SmtpApiError::TooLarge { len: 44, max: 20 }, error);
+    assert_eq!("the header is 44 bytes long, the limit is 20", error.to_string());
+}
+
+#[test]
+fn header_schedule() {
+    let mut header = Header::new();
+    header.add_tos(vec!["foo1@domain.com", "foo2@domain.com"]);
+    assert_eq!(Schedule::Immediate, header.schedule());
+
+    header.set_schedule(Schedule::At(1453213937));
+    assert_eq!("{\"send_at\":1453213937,\"to\":[\"foo1@domain.com\",\"foo2@domain.com\"]}", header.to_json_string());
+
+    assert_eq!(Err(SmtpApiError::ScheduleConflict), header.try_set_schedule(Schedule::At(1453213939)).map(|_| ()));
+    assert_eq!(Err(SmtpApiError::ScheduleConflict), header.try_set_schedule(Schedule::Immediate).map(|_| ()));
+    assert_eq!(Some(1453213937), header.send_at());
+
+    header.set_schedule(Schedule::Immediate)
+          .try_set_schedule(Schedule::EachAt(vec![1453213937, 1453213939]))
+          .unwrap();
+    assert_eq!(Schedule::EachAt(vec![1453213937, 1453213939]), header.schedule());
+    assert_eq!(None, header.send_at());
+
+    header.set_schedule(Schedule::At(1453213937));
+    assert_eq!(Err(SmtpApiError::ScheduleConflict), header.try_add_send_each_at(1453213939).map(|_| ()));
+    assert_eq!(Schedule::At(1453213937), header.schedule());
+
+    let mut parsed = Header::from_json_str("{\"send_at\":1453213937,\"send_each_at\":[1453213939]}").unwrap();
+    assert_eq!(Schedule::EachAt(vec![1453213939]), parsed.schedule());
+    assert_eq!(Err(SmtpApiError::ScheduleConflict), parsed.try_set_schedule(Schedule::EachAt(vec![1453213939])).map(|_| ()));
+}
+
+#[test]
+fn header_with_typed_send_times() {
+    let now = SystemTime::now();
+    let in_an_hour = now + Duration::from_secs(3600);
+
+    let mut header = Header::new();
+    header.set_send_at_time(in_an_hour).unwrap();
+    assert_eq!(Some(in_an_hour.to_timestamp()), header.send_at());
+    assert_eq!(Some(in_an_hour.to_timestamp()), header.send_at_time::<SystemTime>().map(|time| time.to_timestamp()));
+
+    let too_late = now + Duration::from_secs(MAX_SCHEDULE_SECS as u64 + 3600);
+    match header.set_send_each_at_times(vec![in_an_hour, too_late]) {
+        Err(SmtpApiError::SendTimeOutOfWindow { timestamp, .. }) => assert_eq!(too_late.to_timestamp(), timestamp),
+        other => panic!("unexpected result {:?}", other.map(|_| ())),
+    }
+    assert!(header.add_send_each_at_time(now - Duration::from_secs(60)).is_err());
+    assert_eq!(Err(SmtpApiError::ScheduleConflict), header.add_send_each_at_time(in_an_hour).map(|_| ()));
+    assert_eq!(Schedule::At(in_an_hour.to_timestamp()), header.schedule());
+
+    header.set_send_each_at_times(vec![in_an_hour, in_an_hour + Duration::from_secs(60)]).unwrap();
+    assert_eq!(Some(2), header.send_each_at_times::<SystemTime>().map(|times| times.len()));
+    assert_eq!(None, header.send_at_time::<SystemTime>());
+}
+
+#[test]
+fn header_spread_send_each_at() {
+    let mut header = Header::new();
+    for i in 0..100 {
+        header.add_to(format!("foo{}@domain.com", i));
+    }
+
+    let start = SystemTime::now() + Duration::from_secs(60);
+    header.spread_send_each_at(start, &Spread::over(Duration::from_secs(48 * 3600))).unwrap();
+
+    let send_each_at = header.send_each_at().unwrap().to_vec();
+    assert_eq!(100, send_each_at.len());
+    assert_eq!(start.to_timestamp(), send_each_at[0]);
+    assert!(send_each_at.windows(2).all(|pair| pair[1] - pair[0] == 1728));
+    assert!(header.validate().is_ok());
+
+    let mut jittered = Spread::per_hour(60);
+    jittered.jitter(2016);
+    header.spread_send_each_at(start, &jittered).unwrap();
+    let first = header.send_each_at().unwrap().to_vec();
+    header.spread_send_each_at(start, &jittered).unwrap();
+    assert_eq!(first, header.send_each_at().unwrap());
+    assert!(first.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(first != send_each_at);
+
+    match header.spread_send_each_at(start, &Spread::per_hour(1)) {
+        Err(SmtpApiError::SendTimeOutOfWindow { .. }) => {},
+        other => panic!("unexpected result {:?}", other.map(|_| ())),
+    }
+    assert_eq!(Some(&first[..]), header.send_each_at());
+    assert_eq!(Err(SmtpApiError::ZeroSpreadRate), header.spread_send_each_at(start, &Spread::per_hour(0)).map(|_| ()));
+    assert_eq!(Some(&first[..]), header.send_each_at());
+
+    let mut empty = Header::new();
+    empty.set_schedule(Schedule::At(start.to_timestamp()));
+    assert_eq!(Err(SmtpApiError::NoRecipients), empty.spread_send_each_at(start, &jittered).map(|_| ()));
+    assert_eq!(Schedule::At(start.to_timestamp()), empty.schedule());
+}
+
+#[test]
+fn header_with_batch_id() {
+    let mut header = Header::new();
+    header.add_to("foo1@domain.com")
+          .set_schedule(Schedule::At(1453213937))
+          .set_batch_id("HkJ5yLYULb7Rj8GKSx7u025ouWVlMgAi");
+
+    let json = "{\"batch_id\":\"HkJ5yLYULb7Rj8GKSx7u025ouWVlMgAi\",\"send_at\":1453213937,\"to\":[\"foo1@domain.com\"]}";
+    assert_eq!(json, header.to_json_string());
+    assert_eq!(Some("HkJ5yLYULb7Rj8GKSx7u025ouWVlMgAi"), Header::from_json_str(json).unwrap().batch_id());
+    assert!(header.validate().is_ok());
+    assert!(header.split(1).iter().all(|part| part.batch_id() == header.batch_id()));
+
+    let mut other = header.clone();
+    other.set_batch_id("bad batch");
+    assert_eq!(Err(vec![ValidationError::InvalidBatchId("bad batch".to_string())]), other.validate());
+    assert_eq!(vec![Change::BatchIdChanged { old: Some("HkJ5yLYULb7Rj8GKSx7u025ouWVlMgAi".to_string()), new: Some("bad batch".to_string()) }], header.diff(&other));
+
+    other.set_batch_id("");
+    assert_eq!("batch_id", other.validate().unwrap_err()[0].field());
+
+    header.unset_batch_id();
+    assert_eq!(None, header.batch_id());
+}
+
+#[test]
+fn header_with_strict_tos() {
+    let mut header = Header::new();
+    header.try_add_to("foo1@domain.com").unwrap()
+          .try_add_tos(vec!["Foo Two <foo2@domain.com>", "\"foo three\"@domain.com"]).unwrap();
+
+    let errors = header.try_add_tos(vec!["foo4@domain.com", "bob@", "Bob Smith bob@domain.com", "foo5@domain..com"]).unwrap_err();
+    assert_eq!(vec![
+        SmtpApiError::InvalidAddress { index: 4, email: "bob@".to_string(), reason: AddressError::InvalidDomain },
+        SmtpApiError::InvalidAddress { index: 5, email: "Bob Smith bob@domain.com".to_string(), reason: AddressError::InvalidLocalPart },
+        SmtpApiError::InvalidAddress { index: 6, email: "foo5@domain..com".to_string(), reason: AddressError::InvalidDomain },
+    ], errors);
+    assert_eq!("to[4] `bob@` is not a valid email address: the domain is invalid", errors[0].to_string());
+    assert_eq!(3, header.tos().len());
+
+    let errors = header.try_set_tos(vec!["", "foo1@domain.com"]).unwrap_err();
+    assert_eq!(vec![SmtpApiError::InvalidAddress { index: 0, email: String::new(), reason: AddressError::Empty }], errors);
+    assert_eq!(3, header.tos().len());
+
+    header.try_set_tos(vec![" foo1@domain.com", "Foo Two <foo2@domain.com>\t"]).unwrap()
+          .try_add_to("\r\nfoo3@domain.com ").unwrap();
+    assert_eq!(["foo1@domain.com", "Foo Two <foo2@domain.com>", "foo3@domain.com"], header.tos());
+
+    let address: EmailAddress = "Foo <FOO1@Domain.com>".parse().unwrap();
+    header.set_tos(vec![address]);
+    assert_eq!(["FOO1@Domain.com"], header.tos());
+}
+
+#[test]
+fn header_with_named_tos() {
+    let mut header = Header::new();
+    header.add_named_to("Foo One", "foo1@domain.com".parse().unwrap())
+          .add_named_to("One, Foo", "foo2@domain.com".parse().unwrap())
+          .add_named_to("天破活殺", "foo3@domain.com".parse().unwrap())
+          .add_to("foo4@domain.com");
+
+    assert_eq!("{\"to\":[\"Foo One <foo1@domain.com>\",\"\\\"One, Foo\\\" <foo2@domain.com>\",\"=?UTF-8?B?5aSp56C05rS75q66?= <foo3@domain.com>\",\"foo4@domain.com\"]}", header.to_json_string());
+    assert!(header.try_set_tos(header.tos().to_vec()).is_ok());
+
+    let parsed = Header::from_json_str(&header.to_json_string()).unwrap();
+    let mailboxes: Vec<Mailbox> = parsed.recipients().map(|r| r.mailbox().unwrap()).collect();
+    assert_eq!(vec![Some("Foo One"), Some("One, Foo"), Some("天破活殺"), None], mailboxes.iter().map(|m| m.name()).collect::<Vec<Option<&str>>>());
+    assert_eq!("foo3@domain.com", mailboxes[2].address().to_string());
+}
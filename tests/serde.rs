@@ -0,0 +1,60 @@
+#![cfg(feature = "serde")]
+
+extern crate smtpapi;
+extern crate serde_json;
+extern crate bincode;
+
+use smtpapi::{Header, Filter, Schedule};
+
+#[test]
+fn header_serializes_to_wire_format() {
+    let mut header = Header::new();
+    header.add_recipient("foo1@domain.com", vec![("-name-", "foo1")]).unwrap()
+          .add_recipient("foo2@domain.com", vec![("-name-", "foo2")]).unwrap()
+          .add_categories(vec!["welcome", "天破活殺"])
+          .add_section("-top-", "foobar")
+          .add_unique_arg("-arg2-", "value2")
+          .add_unique_arg("-arg1-", "value1")
+          .add_filter("footer", "text/html", "<strong>boo</strong>")
+          .add_filter("clicktrack", "enable", "1")
+          .add_filter("footer", "enable", "1")
+          .set_schedule(Schedule::EachAt(vec![1453213937, 1453213939]))
+          .set_asm_group_id(12)
+          .set_batch_id("HkJ5yLYULb7Rj8GKSx7u025ouWVlMgAi")
+          .set_ip_pool("pool_1");
+
+    assert_eq!(header.to_json_string(), serde_json::to_string(&header).unwrap());
+    assert_eq!("{}", serde_json::to_string(&Header::new()).unwrap());
+    assert_eq!("{}", serde_json::to_string(&Filter::new()).unwrap());
+}
+
+#[test]
+fn header_deserializes_from_wire_format() {
+    let json = "{\"asm_group_id\":12,\"batch_id\":\"HkJ5yLYULb7Rj8GKSx7u025ouWVlMgAi\",\"category\":[\"welcome\"],\"filters\":{\"clicktrack\":{\"settings\":{\"enable\":\"1\"}}},\"ip_pool\":\"pool_1\",\"section\":{\"-top-\":\"foobar\"},\"send_at\":1453213937,\"sub\":{\"-name-\":[\"foo\"]},\"to\":[\"foo@domain.com\"],\"unique_args\":{\"-arg1-\":\"value1\"}}";
+    let header: Header = serde_json::from_str(json).unwrap();
+
+    assert_eq!(json, header.to_json_string());
+
+    let header: Header = serde_json::from_str("{\"category\":\"welcome\",\"filters\":{\"opentrack\":{\"settings\":{\"enable\":1}}}}").unwrap();
+    assert_eq!("{\"category\":[\"welcome\"],\"filters\":{\"opentrack\":{\"settings\":{\"enable\":\"1\"}}}}", header.to_json_string());
+
+    assert!(serde_json::from_str::<Header>("{\"categories\":[\"welcome\"]}").is_err());
+    assert!(serde_json::from_str::<Header>("{\"asm_group_id\":\"12\"}").is_err());
+}
+
+#[test]
+fn header_round_trips_through_binary_format() {
+    let mut header = Header::new();
+    header.add_recipient("foo1@domain.com", vec![("-name-", "foo1")]).unwrap()
+          .add_category("welcome")
+          .add_filter("clicktrack", "enable", "1")
+          .set_schedule(Schedule::At(1453213937))
+          .set_ip_pool("pool_1");
+
+    let bytes = bincode::serialize(&header).unwrap();
+    let decoded: Header = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(header.to_json_string(), decoded.to_json_string());
+
+    let bytes = bincode::serialize(&Header::new()).unwrap();
+    assert_eq!("{}", bincode::deserialize::<Header>(&bytes).unwrap().to_json_string());
+}
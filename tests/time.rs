@@ -0,0 +1,73 @@
+#![cfg(any(feature = "chrono", feature = "time"))]
+
+extern crate smtpapi;
+#[cfg(feature = "chrono")]
+extern crate chrono;
+#[cfg(feature = "time")]
+extern crate time;
+
+use smtpapi::Header;
+
+#[cfg(feature = "chrono")]
+#[test]
+fn header_with_chrono_send_times() {
+    use chrono::{DateTime, Duration, Utc};
+    use smtpapi::{SmtpApiError, MAX_SCHEDULE_SECS};
+    use std::time::SystemTime;
+
+    let now: DateTime<Utc> = SystemTime::now().into();
+
+    let in_an_hour = now + Duration::hours(1);
+    let mut header = Header::new();
+
+    header.set_send_at_time(in_an_hour).unwrap();
+    assert_eq!(Some(in_an_hour.timestamp()), header.send_at());
+    assert_eq!(Some(in_an_hour.timestamp()), header.send_at_time::<DateTime<Utc>>().map(|time| time.timestamp()));
+
+    let too_late = now + Duration::seconds(MAX_SCHEDULE_SECS + 60);
+    match header.add_send_each_at_time(too_late) {
+        Err(SmtpApiError::SendTimeOutOfWindow { timestamp, .. }) => assert_eq!(too_late.timestamp(), timestamp),
+        other => panic!("unexpected result {:?}", other.map(|_| ())),
+    }
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn header_with_time_send_times() {
+    use time::{Duration, OffsetDateTime};
+
+    let in_an_hour = OffsetDateTime::now_utc() + Duration::hours(1);
+    let mut header = Header::new();
+
+    header.set_send_each_at_times(vec![in_an_hour]).unwrap();
+    assert_eq!(Some(vec![in_an_hour.unix_timestamp()]), header.send_each_at_times::<OffsetDateTime>().map(|times| times.iter().map(|time| time.unix_timestamp()).collect()));
+
+    let yesterday = OffsetDateTime::now_utc() - Duration::days(1);
+    assert!(header.set_send_at_time(yesterday).is_err());
+}
+
+#[cfg(feature = "timezone")]
+#[test]
+fn header_with_local_send_times() {
+    use chrono::{DateTime, Duration, NaiveTime, Utc};
+    use smtpapi::SmtpApiError;
+    use std::time::SystemTime;
+
+    let now: DateTime<Utc> = SystemTime::now().into();
+    let nine_am = (now + Duration::days(2)).date_naive().and_time(NaiveTime::from_hms_opt(9, 0, 0).unwrap());
+
+    let mut header = Header::new();
+    header.add_tos(vec!["foo1@domain.com", "foo2@domain.com", "foo3@domain.com"]);
+
+    assert_eq!(Err(SmtpApiError::MisalignedSendEachAt { expected: 3, found: 1 }),
+               header.set_send_each_at_local(nine_am, &["Europe/Paris"]).map(|_| ()));
+    assert_eq!(Err(SmtpApiError::UnknownTimezone { index: 2, name: "Europe/Atlantis".to_string() }),
+               header.set_send_each_at_local(nine_am, &["Europe/Paris", "Asia/Tokyo", "Europe/Atlantis"]).map(|_| ()));
+    assert_eq!(None, header.send_each_at());
+
+    header.set_send_each_at_local(nine_am, &["Asia/Tokyo", "Asia/Tokyo", "UTC"]).unwrap();
+    let send_each_at = header.send_each_at().unwrap();
+    assert_eq!(send_each_at[0], send_each_at[1]);
+    assert_eq!(9 * 3600, send_each_at[2] - send_each_at[0]);
+    assert_eq!(nine_am.and_utc().timestamp(), send_each_at[2]);
+}